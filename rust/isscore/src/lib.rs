@@ -1,10 +1,11 @@
 // lib.rs - ISS Transit Prediction Library for Flutter/FFI
 // Rewritten using proven main.rs algorithm (direct scanning without pass pre-filtering)
 
-use std::ffi::{CStr, CString, c_char};
+use std::ffi::{CStr, CString, c_char, c_void};
 use std::f64::consts::PI;
+use std::collections::BTreeMap;
 use serde::Serialize;
-use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc};
 use log::{info, warn};
 use std::sync::Once;
 
@@ -19,6 +20,64 @@ const ISS_DIMENSION_M: f64 = 108.0;
 const EARTH_RADIUS_KM: f64 = 6378.137; // WGS-84
 const EARTH_FLATTENING: f64 = 1.0 / 298.257_223_563;
 const EARTH_E2: f64 = EARTH_FLATTENING * (2.0 - EARTH_FLATTENING);
+const DEFAULT_PRESSURE_HPA: f64 = 1010.0;
+const DEFAULT_TEMPERATURE_C: f64 = 10.0;
+/// Sentinel threshold for "temperature not supplied" on the FFI surface.
+/// Unlike pressure, Celsius temperatures are legitimately negative, so a
+/// plain `< 0.0` check can't distinguish "unset" from "caller wants -5°C";
+/// physical temperatures can't go below absolute zero, so that's used
+/// instead.
+const ABSOLUTE_ZERO_C: f64 = -273.15;
+
+// ============================================================================
+// Atmospheric Refraction
+// ============================================================================
+
+/// Pressure/temperature used to scale the refraction correction. Defaults
+/// match a standard atmosphere at sea level (1010 hPa, 10 °C).
+#[derive(Debug, Clone, Copy)]
+struct RefractionParams {
+    pressure_hpa: f64,
+    temperature_c: f64,
+}
+
+impl Default for RefractionParams {
+    fn default() -> Self {
+        Self {
+            pressure_hpa: DEFAULT_PRESSURE_HPA,
+            temperature_c: DEFAULT_TEMPERATURE_C,
+        }
+    }
+}
+
+/// Resolves the FFI-facing `pressure_hpa`/`temperature_c` parameters into a
+/// `RefractionParams`, independently defaulting each one: a negative
+/// `pressure_hpa` falls back to the standard-atmosphere pressure, and a
+/// `temperature_c` at or below absolute zero falls back to the
+/// standard-atmosphere temperature — so a caller can override just one of
+/// the two without losing the other's default.
+fn resolve_refraction(pressure_hpa: f64, temperature_c: f64) -> RefractionParams {
+    RefractionParams {
+        pressure_hpa: if pressure_hpa < 0.0 { DEFAULT_PRESSURE_HPA } else { pressure_hpa },
+        temperature_c: if temperature_c <= ABSOLUTE_ZERO_C { DEFAULT_TEMPERATURE_C } else { temperature_c },
+    }
+}
+
+impl RefractionParams {
+    /// Bennett's formula for the true -> apparent altitude correction,
+    /// scaled by pressure/temperature and clamped to zero below about -1°
+    /// (where the approximation blows up and bodies are below the horizon
+    /// anyway).
+    fn apparent_altitude_deg(&self, h_deg: f64) -> f64 {
+        if h_deg < -1.0 {
+            return h_deg;
+        }
+        let r_arcmin = 1.0 / (h_deg + 7.31 / (h_deg + 4.4)).to_radians().tan();
+        let r_arcmin = r_arcmin.max(0.0);
+        let scale = (self.pressure_hpa / 1010.0) * (283.0 / (273.0 + self.temperature_c));
+        h_deg + (r_arcmin * scale) / 60.0
+    }
+}
 
 // ============================================================================
 // Simple Vector3 (from main.rs)
@@ -73,6 +132,176 @@ struct Event {
     duration_s: f64,
     sat_angular_size_arcsec: f64,
     sat_distance_km: f64,
+    ground_track: Vec<GroundTrackPoint>,
+    /// How far short of the combined satellite/body disk radius the
+    /// closest approach fell, in arcminutes; `None` for an actual disk
+    /// transit (`kind` "transit"), which has no miss to report.
+    miss_distance_arcmin: Option<f64>,
+    /// Sun-Moon-Earth phase angle (0° at full moon, 180° at new moon) at
+    /// the transit instant; `None` for every body other than "Moon".
+    moon_phase_angle_deg: Option<f64>,
+    /// Lit fraction of the Moon's disk at the transit instant; `None` for
+    /// every body other than "Moon".
+    moon_illuminated_fraction: Option<f64>,
+    /// Position angle (from north, through east) of the Moon's bright
+    /// limb; `None` for every body other than "Moon".
+    moon_bright_limb_angle_deg: Option<f64>,
+    /// Whether the satellite's crossing point falls on the Moon's
+    /// "illuminated" or "shadowed" side; `None` for every body other than
+    /// "Moon".
+    moon_crossing_side: Option<String>,
+}
+
+/// One sample of the transit center line: the geodetic point where the
+/// satellite appears exactly centered on the body's disk, plus the
+/// half-width (km either side of the line) within which the satellite's
+/// silhouette still overlaps the disk.
+#[derive(Serialize, Debug, Clone)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+struct GroundTrackPoint {
+    time_utc: String,
+    lat_deg: f64,
+    lon_deg: f64,
+    half_width_km: f64,
+}
+
+/// Bumped whenever `Event`'s field set or types change; carried in the
+/// columnar JSON output (`ColumnarEvents::schema_version`) so callers can
+/// detect a format change without guessing from key presence. The classic
+/// array output does not include it.
+const EVENT_SCHEMA_VERSION: u32 = 4;
+
+/// Opt-in alternative to the per-event JSON array: one object holding
+/// `schema_version`, a `column_types` map (`"string"`/`"number"`/`"array"`
+/// per field), and the same `Event` fields as parallel arrays instead of
+/// repeated per-object keys. Cuts key-string overhead on large result sets;
+/// selected via the `columnar` flag on `predict_transits`,
+/// `predict_transits_sp3`, and `predict_transits_multi`.
+#[derive(Serialize)]
+struct ColumnarEvents {
+    schema_version: u32,
+    column_types: BTreeMap<&'static str, &'static str>,
+    time_utc: Vec<String>,
+    body: Vec<String>,
+    separation_arcmin: Vec<f64>,
+    target_radius_arcmin: Vec<f64>,
+    kind: Vec<String>,
+    sat_alt_deg: Vec<f64>,
+    sat_az_deg: Vec<f64>,
+    target_alt_deg: Vec<f64>,
+    satellite: Vec<String>,
+    speed_deg_per_s: Vec<f64>,
+    speed_arcmin_per_s: Vec<f64>,
+    velocity_alt_deg_per_s: Vec<f64>,
+    velocity_az_deg_per_s: Vec<f64>,
+    motion_direction_deg: Vec<f64>,
+    duration_s: Vec<f64>,
+    sat_angular_size_arcsec: Vec<f64>,
+    sat_distance_km: Vec<f64>,
+    ground_track: Vec<Vec<GroundTrackPoint>>,
+    miss_distance_arcmin: Vec<Option<f64>>,
+    moon_phase_angle_deg: Vec<Option<f64>>,
+    moon_illuminated_fraction: Vec<Option<f64>>,
+    moon_bright_limb_angle_deg: Vec<Option<f64>>,
+    moon_crossing_side: Vec<Option<String>>,
+}
+
+impl From<Vec<Event>> for ColumnarEvents {
+    fn from(events: Vec<Event>) -> Self {
+        let mut column_types = BTreeMap::new();
+        column_types.insert("time_utc", "string");
+        column_types.insert("body", "string");
+        column_types.insert("separation_arcmin", "number");
+        column_types.insert("target_radius_arcmin", "number");
+        column_types.insert("kind", "string");
+        column_types.insert("sat_alt_deg", "number");
+        column_types.insert("sat_az_deg", "number");
+        column_types.insert("target_alt_deg", "number");
+        column_types.insert("satellite", "string");
+        column_types.insert("speed_deg_per_s", "number");
+        column_types.insert("speed_arcmin_per_s", "number");
+        column_types.insert("velocity_alt_deg_per_s", "number");
+        column_types.insert("velocity_az_deg_per_s", "number");
+        column_types.insert("motion_direction_deg", "number");
+        column_types.insert("duration_s", "number");
+        column_types.insert("sat_angular_size_arcsec", "number");
+        column_types.insert("sat_distance_km", "number");
+        column_types.insert("ground_track", "array");
+        column_types.insert("miss_distance_arcmin", "number");
+        column_types.insert("moon_phase_angle_deg", "number");
+        column_types.insert("moon_illuminated_fraction", "number");
+        column_types.insert("moon_bright_limb_angle_deg", "number");
+        column_types.insert("moon_crossing_side", "string");
+
+        let mut columns = ColumnarEvents {
+            schema_version: EVENT_SCHEMA_VERSION,
+            column_types,
+            time_utc: Vec::with_capacity(events.len()),
+            body: Vec::with_capacity(events.len()),
+            separation_arcmin: Vec::with_capacity(events.len()),
+            target_radius_arcmin: Vec::with_capacity(events.len()),
+            kind: Vec::with_capacity(events.len()),
+            sat_alt_deg: Vec::with_capacity(events.len()),
+            sat_az_deg: Vec::with_capacity(events.len()),
+            target_alt_deg: Vec::with_capacity(events.len()),
+            satellite: Vec::with_capacity(events.len()),
+            speed_deg_per_s: Vec::with_capacity(events.len()),
+            speed_arcmin_per_s: Vec::with_capacity(events.len()),
+            velocity_alt_deg_per_s: Vec::with_capacity(events.len()),
+            velocity_az_deg_per_s: Vec::with_capacity(events.len()),
+            motion_direction_deg: Vec::with_capacity(events.len()),
+            duration_s: Vec::with_capacity(events.len()),
+            sat_angular_size_arcsec: Vec::with_capacity(events.len()),
+            sat_distance_km: Vec::with_capacity(events.len()),
+            ground_track: Vec::with_capacity(events.len()),
+            miss_distance_arcmin: Vec::with_capacity(events.len()),
+            moon_phase_angle_deg: Vec::with_capacity(events.len()),
+            moon_illuminated_fraction: Vec::with_capacity(events.len()),
+            moon_bright_limb_angle_deg: Vec::with_capacity(events.len()),
+            moon_crossing_side: Vec::with_capacity(events.len()),
+        };
+
+        for e in events {
+            columns.time_utc.push(e.time_utc);
+            columns.body.push(e.body);
+            columns.separation_arcmin.push(e.separation_arcmin);
+            columns.target_radius_arcmin.push(e.target_radius_arcmin);
+            columns.kind.push(e.kind);
+            columns.sat_alt_deg.push(e.sat_alt_deg);
+            columns.sat_az_deg.push(e.sat_az_deg);
+            columns.target_alt_deg.push(e.target_alt_deg);
+            columns.satellite.push(e.satellite);
+            columns.speed_deg_per_s.push(e.speed_deg_per_s);
+            columns.speed_arcmin_per_s.push(e.speed_arcmin_per_s);
+            columns.velocity_alt_deg_per_s.push(e.velocity_alt_deg_per_s);
+            columns.velocity_az_deg_per_s.push(e.velocity_az_deg_per_s);
+            columns.motion_direction_deg.push(e.motion_direction_deg);
+            columns.duration_s.push(e.duration_s);
+            columns.sat_angular_size_arcsec.push(e.sat_angular_size_arcsec);
+            columns.sat_distance_km.push(e.sat_distance_km);
+            columns.ground_track.push(e.ground_track);
+            columns.miss_distance_arcmin.push(e.miss_distance_arcmin);
+            columns.moon_phase_angle_deg.push(e.moon_phase_angle_deg);
+            columns.moon_illuminated_fraction.push(e.moon_illuminated_fraction);
+            columns.moon_bright_limb_angle_deg.push(e.moon_bright_limb_angle_deg);
+            columns.moon_crossing_side.push(e.moon_crossing_side);
+        }
+
+        columns
+    }
+}
+
+/// Serializes `events` either as the classic JSON array of per-event
+/// objects, or (when `columnar` is set) as one `ColumnarEvents` object of
+/// parallel arrays. Used by every batch-returning FFI function so they stay
+/// consistent as new output modes are added.
+fn serialize_events(events: Vec<Event>, columnar: bool) -> String {
+    if columnar {
+        let columns: ColumnarEvents = events.into();
+        serde_json::to_string(&columns).unwrap_or_else(|_| "{}".to_string())
+    } else {
+        serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string())
+    }
 }
 
 // ============================================================================
@@ -128,6 +357,90 @@ fn datetime_to_jd(dt: DateTime<Utc>) -> f64 {
         + day + frac + b - 1524.5
 }
 
+// ============================================================================
+// Time Scales (TT, UT1, Delta-T)
+// ============================================================================
+//
+// `datetime_to_jd` treats its input as a plain calendar instant, so to get
+// a Julian date on a particular scale we first shift the `DateTime<Utc>`
+// by that scale's offset from UTC, then hand the shifted instant to
+// `datetime_to_jd` as usual.
+
+/// TAI − UTC (leap seconds) after each IERS bulletin, as Unix timestamps
+/// of the instant the new offset took effect. Holds at the last entry
+/// for dates past 2017-01-01, matching the real-world leap-second freeze
+/// that has held since then.
+const LEAP_SECOND_TABLE: &[(i64, f64)] = &[
+    (63072000, 10.0),   // 1972-01-01
+    (78796800, 11.0),   // 1972-07-01
+    (94694400, 12.0),   // 1973-01-01
+    (126230400, 13.0),  // 1974-01-01
+    (157766400, 14.0),  // 1975-01-01
+    (189302400, 15.0),  // 1976-01-01
+    (220924800, 16.0),  // 1977-01-01
+    (252460800, 17.0),  // 1978-01-01
+    (283996800, 18.0),  // 1979-01-01
+    (315532800, 19.0),  // 1980-01-01
+    (362793600, 20.0),  // 1981-07-01
+    (394329600, 21.0),  // 1982-07-01
+    (425865600, 22.0),  // 1983-07-01
+    (489024000, 23.0),  // 1985-07-01
+    (567993600, 24.0),  // 1988-01-01
+    (631152000, 25.0),  // 1990-01-01
+    (662688000, 26.0),  // 1991-01-01
+    (709948800, 27.0),  // 1992-07-01
+    (741484800, 28.0),  // 1993-07-01
+    (773020800, 29.0),  // 1994-07-01
+    (820454400, 30.0),  // 1996-01-01
+    (867715200, 31.0),  // 1997-07-01
+    (915148800, 32.0),  // 1999-01-01
+    (1136073600, 33.0), // 2006-01-01
+    (1230768000, 34.0), // 2009-01-01
+    (1341100800, 35.0), // 2012-07-01
+    (1435708800, 36.0), // 2015-07-01
+    (1483228800, 37.0), // 2017-01-01
+];
+
+/// TAI − UTC (leap seconds) in effect at `dt`.
+fn tai_minus_utc_s(dt: DateTime<Utc>) -> f64 {
+    let ts = dt.timestamp();
+    let mut offset = LEAP_SECOND_TABLE[0].1;
+    for &(epoch, leap) in LEAP_SECOND_TABLE {
+        if ts >= epoch {
+            offset = leap;
+        } else {
+            break;
+        }
+    }
+    offset
+}
+
+/// Terrestrial Time instant for a UTC `dt`: TT = UTC + (TAI − UTC) +
+/// 32.184 s, represented as a `DateTime<Utc>` shifted by that offset so
+/// it can be fed straight into `datetime_to_jd`.
+fn tt_from_utc(dt: DateTime<Utc>) -> DateTime<Utc> {
+    let offset_s = tai_minus_utc_s(dt) + 32.184;
+    dt + Duration::milliseconds((offset_s * 1000.0).round() as i64)
+}
+
+/// ΔT = TT − UT1 via the Espenak-Meeus polynomial fit for 2005-2050, the
+/// only range this library's predictions ever touch.
+fn delta_t_s(dt: DateTime<Utc>) -> f64 {
+    let t = dt.year() as f64 - 2000.0 + (dt.month() as f64 - 0.5) / 12.0;
+    62.92 + 0.32217 * t + 0.005589 * t * t
+}
+
+/// UT1 instant for a UTC `dt`: UT1 = UTC + ((TT − UTC) − ΔT), represented
+/// the same way as `tt_from_utc`.
+fn ut1_from_utc(dt: DateTime<Utc>) -> DateTime<Utc> {
+    let tt_minus_utc_s = tai_minus_utc_s(dt) + 32.184;
+    let offset_s = tt_minus_utc_s - delta_t_s(dt);
+    dt + Duration::milliseconds((offset_s * 1000.0).round() as i64)
+}
+
+/// Greenwich Mean Sidereal Time. `jd` must be a UT1 Julian date (see
+/// `ut1_from_utc`) — sidereal time tracks the Earth's actual rotation,
+/// not the leap-second-adjusted UTC clock.
 fn gmst_rad(jd: f64) -> f64 {
     let t = (jd - 2451545.0) / 36525.0;
     let gmst_deg = 280.46061837
@@ -152,6 +465,24 @@ fn rot_z(theta: f64) -> [[f64; 3]; 3] {
     ]
 }
 
+fn rot_x(theta: f64) -> [[f64; 3]; 3] {
+    let (s, c) = theta.sin_cos();
+    [
+        [1.0, 0.0, 0.0],
+        [0.0, c, -s],
+        [0.0, s, c],
+    ]
+}
+
+fn rot_y(theta: f64) -> [[f64; 3]; 3] {
+    let (s, c) = theta.sin_cos();
+    [
+        [c, 0.0, s],
+        [0.0, 1.0, 0.0],
+        [-s, 0.0, c],
+    ]
+}
+
 fn mat_mul_vec(m: &[[f64; 3]; 3], v: &Vector3) -> Vector3 {
     Vector3::new(
         m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
@@ -160,6 +491,83 @@ fn mat_mul_vec(m: &[[f64; 3]; 3], v: &Vector3) -> Vector3 {
     )
 }
 
+fn mat_mul_mat(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            out[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+    out
+}
+
+const ARCSEC_TO_RAD: f64 = PI / (180.0 * 3600.0);
+
+/// IAU-1976 precession matrix (J2000 mean equator/equinox -> mean
+/// equator/equinox of date), via the classic zeta/z/theta angles. The
+/// textbook frame-rotation form is `Rz(-z) * Ry(theta) * Rz(-zeta)`;
+/// `rot_y`/`rot_z` here rotate the vector instead, so every sign flips.
+fn precession_matrix(t_centuries: f64) -> [[f64; 3]; 3] {
+    let t = t_centuries;
+    let zeta = (2306.2181 * t + 0.30188 * t * t + 0.017998 * t * t * t) * ARCSEC_TO_RAD;
+    let z = (2306.2181 * t + 1.09468 * t * t + 0.018203 * t * t * t) * ARCSEC_TO_RAD;
+    let theta = (2004.3109 * t - 0.42665 * t * t - 0.041833 * t * t * t) * ARCSEC_TO_RAD;
+
+    mat_mul_mat(&mat_mul_mat(&rot_z(z), &rot_y(-theta)), &rot_z(zeta))
+}
+
+/// Truncated IAU-1980 nutation: leading terms in the lunar node and the
+/// mean longitudes of the Sun and Moon, good to about 1 arcsecond.
+/// Returns `(delta_psi_rad, delta_eps_rad, mean_obliquity_rad)`.
+fn nutation_angles(t_centuries: f64) -> (f64, f64, f64) {
+    let t = t_centuries;
+    let omega = (125.04452 - 1_934.136_261 * t).to_radians();
+    let l_sun = (280.4665 + 36000.7698 * t).to_radians();
+    let l_moon = (218.3165 + 481267.8813 * t).to_radians();
+
+    let dpsi_arcsec = -17.20 * omega.sin()
+        - 1.32 * (2.0 * l_sun).sin()
+        - 0.23 * (2.0 * l_moon).sin()
+        + 0.21 * (2.0 * omega).sin();
+    let deps_arcsec = 9.20 * omega.cos()
+        + 0.57 * (2.0 * l_sun).cos()
+        + 0.10 * (2.0 * l_moon).cos()
+        - 0.09 * (2.0 * omega).cos();
+
+    let mean_obliquity_rad = (23.439291 - 0.0130042 * t).to_radians();
+
+    (dpsi_arcsec * ARCSEC_TO_RAD, deps_arcsec * ARCSEC_TO_RAD, mean_obliquity_rad)
+}
+
+/// Nutation matrix (mean -> true equator of date; textbook frame-rotation
+/// form `Rx(-eps-deps) * Rz(-dpsi) * Rx(eps)`, written with the
+/// vector-rotating `rot_x`/`rot_z`, hence the flipped signs), plus the
+/// angles needed by the equation of the equinoxes.
+fn nutation_matrix(t_centuries: f64) -> ([[f64; 3]; 3], f64, f64) {
+    let (dpsi, deps, eps) = nutation_angles(t_centuries);
+    let m = mat_mul_mat(&mat_mul_mat(&rot_x(eps + deps), &rot_z(dpsi)), &rot_x(-eps));
+    (m, dpsi, eps)
+}
+
+/// Combined J2000 -> TEME-of-date rotation (precession followed by
+/// nutation), applied to the Sun/Moon ECI vectors so they share SGP4's
+/// TEME frame before differencing with the satellite position.
+fn j2000_to_teme_matrix(jd: f64) -> [[f64; 3]; 3] {
+    let t = (jd - 2451545.0) / 36525.0;
+    let precession = precession_matrix(t);
+    let (nutation, _, _) = nutation_matrix(t);
+    mat_mul_mat(&nutation, &precession)
+}
+
+// SGP4 outputs TEME (True Equator, Mean Equinox) position vectors: the
+// equator is the true equator of date (nutation applied), but the equinox
+// is the *mean* one (no equation-of-equinoxes correction). Rotating TEME to
+// ECEF therefore uses plain GMST, not GAST — applying the equation of the
+// equinoxes on top of TEME would double-correct for the equinox and bias
+// the rotation by a few arcseconds. (An earlier GAST/equation-of-equinoxes
+// implementation lived here; it was unused by every real rotation site and
+// has been removed rather than kept as dead code.)
+
 fn geodetic_to_ecef(lat_rad: f64, lon_rad: f64, alt_m: f64) -> Vector3 {
     let (sin_lat, cos_lat) = lat_rad.sin_cos();
     let n = EARTH_RADIUS_KM / (1.0 - EARTH_E2 * sin_lat * sin_lat).sqrt();
@@ -178,6 +586,78 @@ fn angle_between(a: &Vector3, b: &Vector3) -> f64 {
     (dot / denom).clamp(-1.0, 1.0).acos()
 }
 
+/// Observer-relative (topocentric) position: `body_ecef` minus the
+/// observer's own ECEF position, correcting for parallax. At the Moon's
+/// ~384,400 km distance this shifts the apparent position by up to ~1°
+/// — larger than the lunar disk itself — and even the Sun shifts by
+/// ~8.8″ (the solar parallax), so every body's geocentric vector must be
+/// routed through this before `ecef_to_sez`/`altaz`/`angle_between`.
+fn topocentric(body_ecef: &Vector3, observer_ecef: &Vector3) -> Vector3 {
+    body_ecef.sub(observer_ecef)
+}
+
+fn ecef_to_geodetic(ecef: &Vector3) -> (f64, f64) {
+    let r = (ecef.x * ecef.x + ecef.y * ecef.y).sqrt();
+    let lon_rad = ecef.y.atan2(ecef.x);
+
+    let mut lat_rad = ecef.z.atan2(r);
+    loop {
+        let sin_lat = lat_rad.sin();
+        let n = EARTH_RADIUS_KM / (1.0 - EARTH_E2 * sin_lat * sin_lat).sqrt();
+        let next_lat_rad = (ecef.z + EARTH_E2 * n * sin_lat).atan2(r);
+        if (next_lat_rad - lat_rad).abs() < 1e-10 {
+            lat_rad = next_lat_rad;
+            break;
+        }
+        lat_rad = next_lat_rad;
+    }
+
+    (lat_rad.to_degrees(), lon_rad.to_degrees())
+}
+
+/// Casts the ray from `body_ecef` through `sat_ecef` onward to the WGS-84
+/// ellipsoid and returns the near intersection point (the first crossing
+/// past the satellite, i.e. the ground point directly "behind" it as seen
+/// from the body). Returns `None` if the ray misses the ellipsoid.
+fn line_ellipsoid_intersection(body_ecef: &Vector3, sat_ecef: &Vector3) -> Option<Vector3> {
+    let dir = sat_ecef.sub(body_ecef);
+    let a2 = EARTH_RADIUS_KM * EARTH_RADIUS_KM;
+    let b2 = {
+        let b = EARTH_RADIUS_KM * (1.0 - EARTH_E2).sqrt();
+        b * b
+    };
+
+    let qa = dir.x * dir.x / a2 + dir.y * dir.y / a2 + dir.z * dir.z / b2;
+    let qb = 2.0 * (sat_ecef.x * dir.x / a2 + sat_ecef.y * dir.y / a2 + sat_ecef.z * dir.z / b2);
+    let qc = sat_ecef.x * sat_ecef.x / a2 + sat_ecef.y * sat_ecef.y / a2
+        + sat_ecef.z * sat_ecef.z / b2
+        - 1.0;
+
+    let disc = qb * qb - 4.0 * qa * qc;
+    if disc < 0.0 || qa.abs() < 1e-30 {
+        return None;
+    }
+    let sqrt_disc = disc.sqrt();
+    let s1 = (-qb - sqrt_disc) / (2.0 * qa);
+    let s2 = (-qb + sqrt_disc) / (2.0 * qa);
+
+    // Near root: the smallest non-negative parameter, i.e. the first
+    // crossing as we continue past the satellite toward the ellipsoid.
+    let s = if s1 >= 0.0 {
+        s1
+    } else if s2 >= 0.0 {
+        s2
+    } else {
+        return None;
+    };
+
+    Some(Vector3::new(
+        sat_ecef.x + s * dir.x,
+        sat_ecef.y + s * dir.y,
+        sat_ecef.z + s * dir.z,
+    ))
+}
+
 // ============================================================================
 // Celestial Body Positions (from main.rs)
 // ============================================================================
@@ -231,6 +711,259 @@ fn moon_position_eci(jd: f64) -> Vector3 {
     )
 }
 
+/// Right ascension/declination (radians) of an equatorial ECI vector.
+fn ra_dec_rad(v: &Vector3) -> (f64, f64) {
+    let ra = v.y.atan2(v.x);
+    let dec = (v.z / v.norm()).asin();
+    (ra, dec)
+}
+
+/// Position angle (radians, from north through east) of an object at
+/// `(ra_to, dec_to)` as seen from an object at `(ra_from, dec_from)`.
+fn position_angle_rad(ra_from: f64, dec_from: f64, ra_to: f64, dec_to: f64) -> f64 {
+    let d_ra = ra_to - ra_from;
+    (dec_to.cos() * d_ra.sin())
+        .atan2(dec_to.sin() * dec_from.cos() - dec_to.cos() * dec_from.sin() * d_ra.cos())
+}
+
+/// Lunar phase angle, illuminated fraction, and bright-limb position
+/// angle (measured from north, through east) at Julian date `jd`.
+/// `phase_angle_deg` is the Sun-Moon-Earth angle, 0° at full moon and
+/// 180° at new moon; `illuminated_fraction` is `(1 + cos i) / 2`.
+struct MoonIllumination {
+    phase_angle_deg: f64,
+    illuminated_fraction: f64,
+    bright_limb_angle_deg: f64,
+}
+
+/// Computes `MoonIllumination` from `sun_position_eci`/`moon_position_eci`,
+/// so a Moon transit report can state whether the satellite's crossing
+/// azimuth falls on the illuminated or shadowed side of the disk.
+fn moon_illumination(jd: f64) -> MoonIllumination {
+    let sun_eci = sun_position_eci(jd);
+    let moon_eci = moon_position_eci(jd);
+
+    let moon_to_sun = sun_eci.sub(&moon_eci);
+    let moon_to_earth = Vector3::new(-moon_eci.x, -moon_eci.y, -moon_eci.z);
+    let phase_angle_rad = angle_between(&moon_to_sun, &moon_to_earth);
+    let illuminated_fraction = (1.0 + phase_angle_rad.cos()) / 2.0;
+
+    let (ra_sun, dec_sun) = ra_dec_rad(&sun_eci);
+    let (ra_moon, dec_moon) = ra_dec_rad(&moon_eci);
+
+    let bright_limb_angle_rad = position_angle_rad(ra_moon, dec_moon, ra_sun, dec_sun);
+    let bright_limb_angle_deg = bright_limb_angle_rad.to_degrees().rem_euclid(360.0);
+
+    MoonIllumination {
+        phase_angle_deg: phase_angle_rad.to_degrees(),
+        illuminated_fraction,
+        bright_limb_angle_deg,
+    }
+}
+
+/// Classifies which side of the Moon's disk a transiting satellite falls
+/// on: "illuminated" or "shadowed". Near full/new moon the whole disk is
+/// almost uniformly lit/dark, so the position-angle comparison only
+/// applies away from those extremes.
+fn moon_crossing_side(illum: &MoonIllumination, sat_position_angle_deg: f64) -> &'static str {
+    if illum.illuminated_fraction >= 0.98 {
+        return "illuminated";
+    }
+    if illum.illuminated_fraction <= 0.02 {
+        return "shadowed";
+    }
+    let delta = (sat_position_angle_deg - illum.bright_limb_angle_deg).rem_euclid(360.0);
+    if delta <= 90.0 || delta >= 270.0 {
+        "illuminated"
+    } else {
+        "shadowed"
+    }
+}
+
+// Mean radii (km) of the bright planets, for apparent angular size.
+const VENUS_RADIUS_KM: f64 = 6_051.8;
+const MARS_RADIUS_KM: f64 = 3_389.5;
+const JUPITER_RADIUS_KM: f64 = 69_911.0;
+const SATURN_RADIUS_KM: f64 = 58_232.0;
+
+/// J2000 mean orbital elements and their per-Julian-century secular
+/// rates (Standish 1800-2050 fit), used by `planet_position_eci`.
+struct PlanetElements {
+    a0: f64,
+    a_rate: f64,
+    e0: f64,
+    e_rate: f64,
+    i0_deg: f64,
+    i_rate: f64,
+    l0_deg: f64,
+    l_rate: f64,
+    peri0_deg: f64,
+    peri_rate: f64,
+    node0_deg: f64,
+    node_rate: f64,
+}
+
+const EARTH_ELEMENTS: PlanetElements = PlanetElements {
+    a0: 1.000_002_61, a_rate: 0.000_005_62,
+    e0: 0.016_711_23, e_rate: -0.000_043_92,
+    i0_deg: -0.000_015_31, i_rate: -0.013_946_68,
+    l0_deg: 100.464_571_66, l_rate: 35_999.372_449_81,
+    peri0_deg: 102.937_681_93, peri_rate: 0.323_273_64,
+    node0_deg: 0.0, node_rate: 0.0,
+};
+
+fn planet_elements(planet: &str) -> Option<PlanetElements> {
+    match planet {
+        "Venus" => Some(PlanetElements {
+            a0: 0.723_335_66, a_rate: 0.000_003_90,
+            e0: 0.006_776_72, e_rate: -0.000_041_07,
+            i0_deg: 3.394_676_05, i_rate: -0.000_788_90,
+            l0_deg: 181.979_099_50, l_rate: 58_517.815_387_29,
+            peri0_deg: 131.602_467_18, peri_rate: 0.002_683_29,
+            node0_deg: 76.679_842_55, node_rate: -0.277_694_18,
+        }),
+        "Mars" => Some(PlanetElements {
+            a0: 1.523_710_34, a_rate: 0.000_018_47,
+            e0: 0.093_394_10, e_rate: 0.000_078_82,
+            i0_deg: 1.849_691_42, i_rate: -0.008_131_31,
+            l0_deg: -4.553_432_05, l_rate: 19_140.302_684_99,
+            peri0_deg: -23.943_629_59, peri_rate: 0.444_410_88,
+            node0_deg: 49.559_538_91, node_rate: -0.292_573_43,
+        }),
+        "Jupiter" => Some(PlanetElements {
+            a0: 5.202_887_00, a_rate: -0.000_116_07,
+            e0: 0.048_386_24, e_rate: -0.000_132_53,
+            i0_deg: 1.304_396_95, i_rate: -0.001_837_14,
+            l0_deg: 34.396_440_51, l_rate: 3_034.746_127_75,
+            peri0_deg: 14.728_479_83, peri_rate: 0.212_526_68,
+            node0_deg: 100.473_909_09, node_rate: 0.204_691_06,
+        }),
+        "Saturn" => Some(PlanetElements {
+            a0: 9.536_675_94, a_rate: -0.001_250_60,
+            e0: 0.053_861_79, e_rate: -0.000_509_91,
+            i0_deg: 2.485_991_87, i_rate: 0.001_936_09,
+            l0_deg: 49.954_244_23, l_rate: 1_222.493_622_01,
+            peri0_deg: 92.598_878_31, peri_rate: -0.418_972_16,
+            node0_deg: 113.662_424_48, node_rate: -0.288_677_94,
+        }),
+        _ => None,
+    }
+}
+
+/// Solves Kepler's equation `m_rad = e_anom - e * sin(e_anom)` for the
+/// eccentric anomaly by Newton iteration.
+fn solve_kepler(mean_anomaly_rad: f64, eccentricity: f64) -> f64 {
+    let mut e_anom = mean_anomaly_rad;
+    for _ in 0..10 {
+        let f = e_anom - eccentricity * e_anom.sin() - mean_anomaly_rad;
+        let f_prime = 1.0 - eccentricity * e_anom.cos();
+        let delta = f / f_prime;
+        e_anom -= delta;
+        if delta.abs() < 1e-12 {
+            break;
+        }
+    }
+    e_anom
+}
+
+/// Heliocentric J2000 ecliptic position (AU) from a truncated set of
+/// Keplerian elements: mean elements propagated linearly in time, with
+/// Kepler's equation supplying the periodic (eccentric-anomaly) term —
+/// the same "mean elements + periodic correction" shape as
+/// `moon_position_eci`, just solved via Kepler's equation instead of a
+/// handful of explicit trigonometric series terms.
+fn heliocentric_ecliptic(elements: &PlanetElements, t_centuries: f64) -> Vector3 {
+    let t = t_centuries;
+    let a = elements.a0 + elements.a_rate * t;
+    let e = elements.e0 + elements.e_rate * t;
+    let i = (elements.i0_deg + elements.i_rate * t).to_radians();
+    let l_deg = elements.l0_deg + elements.l_rate * t;
+    let peri_deg = elements.peri0_deg + elements.peri_rate * t;
+    let node_deg = elements.node0_deg + elements.node_rate * t;
+    let omega = (peri_deg - node_deg).to_radians(); // argument of periapsis
+    let node = node_deg.to_radians();
+
+    let mut m_deg = (l_deg - peri_deg) % 360.0;
+    if m_deg > 180.0 {
+        m_deg -= 360.0;
+    } else if m_deg < -180.0 {
+        m_deg += 360.0;
+    }
+    let e_anom = solve_kepler(m_deg.to_radians(), e);
+
+    let x_orb = a * (e_anom.cos() - e);
+    let y_orb = a * (1.0 - e * e).sqrt() * e_anom.sin();
+
+    let (sin_o, cos_o) = omega.sin_cos();
+    let (sin_n, cos_n) = node.sin_cos();
+    let (sin_i, cos_i) = i.sin_cos();
+
+    Vector3::new(
+        (cos_o * cos_n - sin_o * sin_n * cos_i) * x_orb + (-sin_o * cos_n - cos_o * sin_n * cos_i) * y_orb,
+        (cos_o * sin_n + sin_o * cos_n * cos_i) * x_orb + (-sin_o * sin_n + cos_o * cos_n * cos_i) * y_orb,
+        (sin_o * sin_i) * x_orb + (cos_o * sin_i) * y_orb,
+    )
+}
+
+/// Geocentric equatorial position (km) of a bright planet via truncated
+/// Keplerian elements, mirroring `moon_position_eci`'s structure: a mean
+/// position corrected by the orbit's periodic term, then rotated from the
+/// ecliptic to the equator by the J2000 obliquity.
+fn planet_position_eci(jd: f64, planet: &str) -> Option<Vector3> {
+    let elements = planet_elements(planet)?;
+    let t = (jd - 2451545.0) / 36525.0;
+
+    let planet_helio = heliocentric_ecliptic(&elements, t);
+    let earth_helio = heliocentric_ecliptic(&EARTH_ELEMENTS, t);
+    let geocentric_ecl = planet_helio.sub(&earth_helio);
+
+    let eps = 23.439291_f64.to_radians(); // J2000 mean obliquity
+    let (sin_eps, cos_eps) = eps.sin_cos();
+
+    Some(Vector3::new(
+        geocentric_ecl.x * AU_KM,
+        (geocentric_ecl.y * cos_eps - geocentric_ecl.z * sin_eps) * AU_KM,
+        (geocentric_ecl.y * sin_eps + geocentric_ecl.z * cos_eps) * AU_KM,
+    ))
+}
+
+/// Geocentric equatorial position (km) of any supported body, dispatching
+/// to the Sun/Moon analytic series or the planetary Keplerian model.
+fn body_position_eci(jd: f64, body: &str) -> Result<Vector3, String> {
+    match body {
+        "Sun" => Ok(sun_position_eci(jd)),
+        "Moon" => Ok(moon_position_eci(jd)),
+        "Venus" | "Mars" | "Jupiter" | "Saturn" => {
+            planet_position_eci(jd, body).ok_or_else(|| format!("Unknown body: {}", body))
+        }
+        _ => Err(format!("Unknown body: {}", body)),
+    }
+}
+
+/// Whether `body` is a stellar-size target (a planet, seen as a
+/// point/arcsecond-scale disk) rather than the Sun or Moon (seen as a
+/// degree-scale disk): a close approach to one of these is reported as a
+/// "conjunction" rather than a "near" miss, since at this angular scale a
+/// literal disk transit is vanishingly rare and "near" would otherwise catch
+/// almost every close approach.
+fn is_stellar_size_target(body: &str) -> bool {
+    matches!(body, "Venus" | "Mars" | "Jupiter" | "Saturn")
+}
+
+/// Mean physical radius (km) used to derive the apparent angular size for
+/// the transit/near-miss disk test.
+fn body_mean_radius_km(body: &str) -> Result<f64, String> {
+    match body {
+        "Sun" => Ok(SUN_RADIUS_KM),
+        "Moon" => Ok(MOON_RADIUS_KM),
+        "Venus" => Ok(VENUS_RADIUS_KM),
+        "Mars" => Ok(MARS_RADIUS_KM),
+        "Jupiter" => Ok(JUPITER_RADIUS_KM),
+        "Saturn" => Ok(SATURN_RADIUS_KM),
+        _ => Err(format!("Unknown body: {}", body)),
+    }
+}
+
 fn altaz(topo_vec: &Vector3) -> (f64, f64) {
     let range = topo_vec.norm();
     let alt_rad = (topo_vec.z / range).asin();
@@ -248,6 +981,282 @@ fn altaz(topo_vec: &Vector3) -> (f64, f64) {
     (alt_rad.to_degrees(), az_deg)
 }
 
+// ============================================================================
+// Pluggable Ephemeris Backends
+// ============================================================================
+
+/// Source of geocentric equatorial Sun/Moon positions (km) at a TT Julian
+/// date. Lets the transit pipeline swap in a higher-precision kernel
+/// without touching any of the geometry code that consumes the result.
+trait Ephemeris {
+    fn sun_eci(&self, tt_jd: f64) -> Vector3;
+    fn moon_eci(&self, tt_jd: f64) -> Vector3;
+}
+
+/// The truncated analytic series (`sun_position_eci`/`moon_position_eci`):
+/// dependency-free and accurate to a few arcminutes. This is the default
+/// backend everywhere in this crate.
+struct AnalyticEphemeris;
+
+impl Ephemeris for AnalyticEphemeris {
+    fn sun_eci(&self, tt_jd: f64) -> Vector3 {
+        sun_position_eci(tt_jd)
+    }
+
+    fn moon_eci(&self, tt_jd: f64) -> Vector3 {
+        moon_position_eci(tt_jd)
+    }
+}
+
+/// Geocentric equatorial position (km) of any supported body, routing
+/// Sun/Moon through `ephemeris` so callers can opt into a higher-precision
+/// backend; planets stay on the analytic Keplerian model regardless.
+fn body_position_eci_via(ephemeris: &dyn Ephemeris, jd: f64, body: &str) -> Result<Vector3, String> {
+    match body {
+        "Sun" => Ok(ephemeris.sun_eci(jd)),
+        "Moon" => Ok(ephemeris.moon_eci(jd)),
+        _ => body_position_eci(jd, body),
+    }
+}
+
+// ---- JPL SPK (.bsp) binary kernel reader ----
+//
+// Implements just enough of the NAIF "DAF" binary format to read a
+// DE-series kernel's Sun and Moon segments: the file record, the summary
+// records listing each segment's body/center/time coverage, and the
+// Type 2/3 (Chebyshev position[/velocity]) data records. See NAIF's "SPK
+// Required Reading" for the full format; kernels using other segment
+// types are rejected with an error rather than silently mis-evaluated.
+
+const NAIF_BODY_SUN: i32 = 10;
+const NAIF_BODY_EARTH_MOON_BARYCENTER: i32 = 3;
+const NAIF_BODY_MOON: i32 = 301;
+const NAIF_BODY_EARTH: i32 = 399;
+const NAIF_CENTER_SSB: i32 = 0;
+const SPK_RECORD_LEN: usize = 1024;
+
+fn read_i32_le(bytes: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn read_f64_le(bytes: &[u8], offset: usize) -> f64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    f64::from_le_bytes(buf)
+}
+
+/// Evaluates `sum(coeffs[k] * T_k(tau))` via the Clenshaw recurrence,
+/// `tau` in `[-1, 1]`.
+fn chebyshev_eval(coeffs: &[f64], tau: f64) -> f64 {
+    let n = coeffs.len();
+    if n == 0 {
+        return 0.0;
+    }
+    let mut b1 = 0.0;
+    let mut b2 = 0.0;
+    for &c in coeffs.iter().skip(1).rev() {
+        let b0 = 2.0 * tau * b1 - b2 + c;
+        b2 = b1;
+        b1 = b0;
+    }
+    coeffs[0] + tau * b1 - b2
+}
+
+/// One SPK segment summary: which body it covers relative to which
+/// center, over what time span, and where its Chebyshev data records
+/// live in the file (as 1-based 8-byte-word addresses, per the DAF spec).
+#[derive(Debug, Clone, Copy)]
+struct SpkSegment {
+    target: i32,
+    center: i32,
+    start_et: f64,
+    end_et: f64,
+    start_addr: usize,
+    end_addr: usize,
+    data_type: i32,
+}
+
+/// A parsed JPL SPK (`.bsp`) kernel, holding the segment directory needed
+/// to evaluate Sun and Moon positions relative to Earth.
+struct SpkEphemeris {
+    bytes: Vec<u8>,
+    segments: Vec<SpkSegment>,
+}
+
+impl SpkEphemeris {
+    /// Parses a `.bsp` kernel already read into memory, walking the
+    /// linked list of summary records starting at the file record's
+    /// forward pointer.
+    fn load(bytes: Vec<u8>) -> Result<Self, String> {
+        if bytes.len() < SPK_RECORD_LEN * 2 {
+            return Err("SPK file too small to contain a file record".to_string());
+        }
+        if &bytes[0..8] != b"DAF/SPK " {
+            return Err("not a DAF/SPK kernel".to_string());
+        }
+
+        let nd = read_i32_le(&bytes, 8) as usize;
+        let ni = read_i32_le(&bytes, 12) as usize;
+        let fward = read_i32_le(&bytes, 76) as usize;
+        let summary_words = nd + (ni + 1) / 2;
+        let summaries_per_record = (SPK_RECORD_LEN / 8 - 3) / summary_words;
+
+        let mut segments = Vec::new();
+        let mut record_num = fward;
+        while record_num != 0 {
+            let record_off = (record_num - 1) * SPK_RECORD_LEN;
+            if record_off + SPK_RECORD_LEN > bytes.len() {
+                break;
+            }
+            let next = read_f64_le(&bytes, record_off) as usize;
+            let n_summaries = read_f64_le(&bytes, record_off + 16) as usize;
+
+            for i in 0..n_summaries.min(summaries_per_record) {
+                let base = record_off + 24 + i * summary_words * 8;
+                let start_et = read_f64_le(&bytes, base);
+                let end_et = read_f64_le(&bytes, base + 8);
+                let int_base = base + nd * 8;
+                segments.push(SpkSegment {
+                    target: read_i32_le(&bytes, int_base),
+                    center: read_i32_le(&bytes, int_base + 4),
+                    data_type: read_i32_le(&bytes, int_base + 12),
+                    start_addr: read_i32_le(&bytes, int_base + 16) as usize,
+                    end_addr: read_i32_le(&bytes, int_base + 20) as usize,
+                    start_et,
+                    end_et,
+                });
+            }
+
+            record_num = next;
+        }
+
+        Ok(Self { bytes, segments })
+    }
+
+    /// Finds the segment for `target` relative to `center` whose time
+    /// span covers `et` (TDB seconds past J2000).
+    fn find_segment(&self, target: i32, center: i32, et: f64) -> Option<&SpkSegment> {
+        self.segments.iter().find(|s| {
+            s.target == target && s.center == center && et >= s.start_et && et <= s.end_et
+        })
+    }
+
+    /// Evaluates a Type 2 (or the position part of a Type 3) Chebyshev
+    /// segment at `et`, returning the body's position (km) relative to
+    /// `segment.center`.
+    fn eval_segment(&self, segment: &SpkSegment, et: f64) -> Result<Vector3, String> {
+        if segment.data_type != 2 && segment.data_type != 3 {
+            return Err(format!("unsupported SPK segment type {}", segment.data_type));
+        }
+
+        // The segment's own directory is its last 4 doubles: INIT,
+        // INTLEN, RSIZE, N.
+        let dir_addr = segment.end_addr - 3;
+        let dir_off = (dir_addr - 1) * 8;
+        let init = read_f64_le(&self.bytes, dir_off);
+        let intlen = read_f64_le(&self.bytes, dir_off + 8);
+        let rsize = read_f64_le(&self.bytes, dir_off + 16) as usize;
+        let n_records = read_f64_le(&self.bytes, dir_off + 24) as usize;
+
+        let record_index = (((et - init) / intlen).floor() as i64).clamp(0, n_records as i64 - 1);
+        let record_addr = segment.start_addr + record_index as usize * rsize;
+        let record_off = (record_addr - 1) * 8;
+
+        let mid = read_f64_le(&self.bytes, record_off);
+        let radius = read_f64_le(&self.bytes, record_off + 8);
+        // Type 2 stores x/y/z coefficients; Type 3 adds vx/vy/vz after them.
+        let n_components = if segment.data_type == 3 { 6 } else { 3 };
+        let n_coeff = (rsize - 2) / n_components;
+        let tau = ((et - mid) / radius).clamp(-1.0, 1.0);
+
+        let component = |comp: usize| -> f64 {
+            let base = record_off + 16 + comp * n_coeff * 8;
+            let coeffs: Vec<f64> = (0..n_coeff).map(|k| read_f64_le(&self.bytes, base + k * 8)).collect();
+            chebyshev_eval(&coeffs, tau)
+        };
+
+        Ok(Vector3::new(component(0), component(1), component(2)))
+    }
+
+    /// Walks the kernel's segment chain from `body` up to the solar
+    /// system barycenter, summing each hop's Chebyshev-evaluated offset.
+    /// Earth is resolved from the Earth-Moon barycenter plus a (negative)
+    /// Moon offset, mirroring how DE-series kernels actually store it.
+    fn position_relative_to_ssb(&self, body: i32, et: f64) -> Result<Vector3, String> {
+        if body == NAIF_BODY_EARTH {
+            let emb = self.position_relative_to_ssb(NAIF_BODY_EARTH_MOON_BARYCENTER, et)?;
+            let moon_segment = self.find_segment(NAIF_BODY_MOON, NAIF_BODY_EARTH_MOON_BARYCENTER, et)
+                .ok_or_else(|| format!("no SPK segment covers body {} at et={}", NAIF_BODY_MOON, et))?;
+            let moon_offset = self.eval_segment(moon_segment, et)?;
+            // Earth sits on the opposite side of the barycenter from the
+            // Moon, `1 / mass_ratio` as far from it as the Moon is.
+            const EARTH_MOON_MASS_RATIO: f64 = 81.30056;
+            let earth_frac = 1.0 / EARTH_MOON_MASS_RATIO;
+            return Ok(Vector3::new(
+                emb.x - moon_offset.x * earth_frac,
+                emb.y - moon_offset.y * earth_frac,
+                emb.z - moon_offset.z * earth_frac,
+            ));
+        }
+
+        let center = if body == NAIF_BODY_MOON { NAIF_BODY_EARTH_MOON_BARYCENTER } else { NAIF_CENTER_SSB };
+        let segment = self.find_segment(body, center, et)
+            .ok_or_else(|| format!("no SPK segment covers body {} at et={}", body, et))?;
+        let offset = self.eval_segment(segment, et)?;
+
+        if center == NAIF_CENTER_SSB {
+            Ok(offset)
+        } else {
+            let center_pos = self.position_relative_to_ssb(center, et)?;
+            Ok(Vector3::new(center_pos.x + offset.x, center_pos.y + offset.y, center_pos.z + offset.z))
+        }
+    }
+
+    /// Geocentric position (km) of `target` at ephemeris time `et` (TDB
+    /// seconds past J2000).
+    fn geocentric_position(&self, target: i32, et: f64) -> Result<Vector3, String> {
+        let earth = self.position_relative_to_ssb(NAIF_BODY_EARTH, et)?;
+        let target_pos = self.position_relative_to_ssb(target, et)?;
+        Ok(target_pos.sub(&earth))
+    }
+}
+
+/// Builds the `Ephemeris` backend for an FFI call from the optional SPK
+/// kernel bytes a caller may supply: a null pointer or zero length means
+/// "no kernel", so the dependency-free analytic series is used; otherwise
+/// the bytes are parsed as a JPL SPK (.bsp) kernel (e.g. DE440) for
+/// arcsecond-level Sun/Moon positions, falling back to the analytic series
+/// if parsing fails.
+///
+/// # Safety
+/// `spk_kernel_data` must point to at least `spk_kernel_len` readable bytes
+/// when non-null, per the usual C FFI buffer contract.
+fn resolve_ephemeris(spk_kernel_data: *const u8, spk_kernel_len: usize) -> Box<dyn Ephemeris> {
+    if spk_kernel_data.is_null() || spk_kernel_len == 0 {
+        return Box::new(AnalyticEphemeris);
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(spk_kernel_data, spk_kernel_len) }.to_vec();
+    match SpkEphemeris::load(bytes) {
+        Ok(eph) => Box::new(eph),
+        Err(e) => {
+            warn!("SPK kernel parse error, falling back to analytic ephemeris: {}", e);
+            Box::new(AnalyticEphemeris)
+        }
+    }
+}
+
+impl Ephemeris for SpkEphemeris {
+    fn sun_eci(&self, tt_jd: f64) -> Vector3 {
+        let et = (tt_jd - 2451545.0) * 86400.0;
+        self.geocentric_position(NAIF_BODY_SUN, et).unwrap_or_else(|_| sun_position_eci(tt_jd))
+    }
+
+    fn moon_eci(&self, tt_jd: f64) -> Vector3 {
+        let et = (tt_jd - 2451545.0) * 86400.0;
+        self.geocentric_position(NAIF_BODY_MOON, et).unwrap_or_else(|_| moon_position_eci(tt_jd))
+    }
+}
+
 // ============================================================================
 // SGP4 Satellite Position (from main.rs)
 // ============================================================================
@@ -272,6 +1281,24 @@ fn get_sat_position(
     ))
 }
 
+/// Source of satellite position, decoupling the transit-detection pipeline
+/// from SGP4 so precise ephemerides (e.g. SP3) can stand in for it. All
+/// implementations return position in the TEME-of-date frame, matching
+/// `get_sat_position`'s convention.
+trait SatellitePosition {
+    fn position_teme(&self, dt: DateTime<Utc>) -> Result<Vector3, String>;
+}
+
+struct Sgp4Satellite<'a> {
+    elements: &'a sgp4::Elements,
+}
+
+impl SatellitePosition for Sgp4Satellite<'_> {
+    fn position_teme(&self, dt: DateTime<Utc>) -> Result<Vector3, String> {
+        get_sat_position(self.elements, dt)
+    }
+}
+
 // ============================================================================
 // Core Transit Detection (from main.rs)
 // ============================================================================
@@ -289,86 +1316,219 @@ fn ecef_to_sez(topo_ecef: &Vector3, lat_rad: f64, lon_rad: f64) -> Vector3 {
     )
 }
 
-fn compute_topo_vectors(
-    elements: &sgp4::Elements,
+/// The per-instant quantities that depend only on the observer and target
+/// body (Sun/Moon/planet), not on which satellite is being checked: the GMST
+/// rotation and the body's topocentric position/altitude. Computing this
+/// once per `(t, body)` and sharing it across every satellite in a
+/// multi-satellite scan (see `scan_transits_multi_for_each`) avoids repeating
+/// the ephemeris lookup and rotation math once per satellite.
+struct BodyFrame {
+    rot_inv: [[f64; 3]; 3],
+    observer_teme: Vector3,
+    body_topo_teme: Vector3,
+    body_alt: f64,
+}
+
+/// Computes the satellite-independent half of `compute_topo_vectors`.
+fn compute_body_frame(
+    ephemeris: &dyn Ephemeris,
     dt: DateTime<Utc>,
     observer_ecef: &Vector3,
     observer_lat_rad: f64,
     observer_lon_rad: f64,
     body: &str,
-) -> Result<(Vector3, Vector3, f64, f64), String> {
-    let sat_teme = get_sat_position(elements, dt)?;
-    let jd_utc = datetime_to_jd(dt);
-    let gmst = gmst_rad(jd_utc);
+    refraction: &RefractionParams,
+) -> Result<BodyFrame, String> {
+    let jd_ut1 = datetime_to_jd(ut1_from_utc(dt));
+    let jd_tt = datetime_to_jd(tt_from_utc(dt));
+    let gmst = gmst_rad(jd_ut1);
     let rot = rot_z(gmst);
-    let observer_teme = mat_mul_vec(&rot, observer_ecef);
-    let sat_topo_teme = sat_teme.sub(&observer_teme);
-    
-    let body_eci = match body {
-        "Sun" => sun_position_eci(jd_utc),
-        "Moon" => moon_position_eci(jd_utc),
-        _ => return Err(format!("Unknown body: {}", body)),
-    };
-    
-    let body_topo_teme = body_eci.sub(&observer_teme);
     let rot_inv = rot_z(-gmst);
-    let sat_topo_ecef = mat_mul_vec(&rot_inv, &sat_topo_teme);
+    let observer_teme = mat_mul_vec(&rot, observer_ecef);
+
+    let body_eci_meandate = body_position_eci_via(ephemeris, jd_tt, body)?;
+    let body_eci = mat_mul_vec(&j2000_to_teme_matrix(jd_tt), &body_eci_meandate);
+    let body_topo_teme = topocentric(&body_eci, &observer_teme);
     let body_topo_ecef = mat_mul_vec(&rot_inv, &body_topo_teme);
-    let sat_topo_sez = ecef_to_sez(&sat_topo_ecef, observer_lat_rad, observer_lon_rad);
     let body_topo_sez = ecef_to_sez(&body_topo_ecef, observer_lat_rad, observer_lon_rad);
-    let (sat_alt, _) = altaz(&sat_topo_sez);
     let (body_alt, _) = altaz(&body_topo_sez);
-    
-    Ok((sat_topo_teme, body_topo_teme, sat_alt, body_alt))
+    let body_alt = refraction.apparent_altitude_deg(body_alt);
+
+    Ok(BodyFrame { rot_inv, observer_teme, body_topo_teme, body_alt })
 }
 
-fn refine_minimum(
-    elements: &sgp4::Elements,
-    t_center: DateTime<Utc>,
-    observer_ecef: &Vector3,
+/// Computes the satellite-dependent half of `compute_topo_vectors` against an
+/// already-computed `BodyFrame`.
+fn sat_topo_in_frame(
+    sat: &dyn SatellitePosition,
+    dt: DateTime<Utc>,
+    frame: &BodyFrame,
     observer_lat_rad: f64,
     observer_lon_rad: f64,
-    body: &str,
-    window_s: f64,
-    step_s: f64,
-) -> Result<(DateTime<Utc>, f64, f64, f64, f64, f64, f64), String> {
-    let n_steps = (window_s / step_s) as i64;
-    let mut min_sep = f64::INFINITY;
-    let mut best_time = t_center;
-    let mut best_sat_alt = 0.0;
-    let mut best_body_alt = 0.0;
-    let mut best_sat_range = 0.0;
-    
-    for i in -n_steps..=n_steps {
-        let t = t_center + Duration::seconds((i as f64 * step_s) as i64);
-        let (sat_topo, body_topo, sat_alt, body_alt) = 
-            compute_topo_vectors(elements, t, observer_ecef, observer_lat_rad, observer_lon_rad, body)?;
-        let sep = angle_between(&sat_topo, &body_topo);
-        
-        if sep < min_sep {
-            min_sep = sep;
-            best_time = t;
-            best_sat_alt = sat_alt;
-            best_body_alt = body_alt;
-            best_sat_range = sat_topo.norm();
+    refraction: &RefractionParams,
+) -> Result<(Vector3, f64), String> {
+    let sat_teme = sat.position_teme(dt)?;
+    let sat_topo_teme = topocentric(&sat_teme, &frame.observer_teme);
+    let sat_topo_ecef = mat_mul_vec(&frame.rot_inv, &sat_topo_teme);
+    let sat_topo_sez = ecef_to_sez(&sat_topo_ecef, observer_lat_rad, observer_lon_rad);
+    let (sat_alt, _) = altaz(&sat_topo_sez);
+    let sat_alt = refraction.apparent_altitude_deg(sat_alt);
+    Ok((sat_topo_teme, sat_alt))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn compute_topo_vectors(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    dt: DateTime<Utc>,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    refraction: &RefractionParams,
+) -> Result<(Vector3, Vector3, f64, f64), String> {
+    let frame = compute_body_frame(ephemeris, dt, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
+    let (sat_topo_teme, sat_alt) = sat_topo_in_frame(sat, dt, &frame, observer_lat_rad, observer_lon_rad, refraction)?;
+    Ok((sat_topo_teme, frame.body_topo_teme, sat_alt, frame.body_alt))
+}
+
+/// Topocentric satellite-body angular separation (radians, TEME frame) at
+/// `offset_s` fractional seconds from `t_center`.
+#[allow(clippy::too_many_arguments)]
+fn separation_at_offset(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    t_center: DateTime<Utc>,
+    offset_s: f64,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    refraction: &RefractionParams,
+) -> Result<f64, String> {
+    let t = t_center + Duration::milliseconds((offset_s * 1000.0) as i64);
+    let (sat_topo, body_topo, _, _) =
+        compute_topo_vectors(sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
+    Ok(angle_between(&sat_topo, &body_topo))
+}
+
+/// Golden-section search for the minimum of `separation_at_offset` inside
+/// `[-window_s, window_s]`, polished with a final parabolic (inverse
+/// quadratic) fit through the last three probed points. `tol_s` is the
+/// bracket-width convergence tolerance, not a sampling step.
+#[allow(clippy::too_many_arguments)]
+fn golden_section_minimize(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    t_center: DateTime<Utc>,
+    window_s: f64,
+    tol_s: f64,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    refraction: &RefractionParams,
+) -> Result<f64, String> {
+    const PHI: f64 = 0.618_033_988_749_895; // (sqrt(5) - 1) / 2
+    const MAX_ITERS: u32 = 100;
+
+    let eval = |offset_s: f64| {
+        separation_at_offset(sat, ephemeris, t_center, offset_s, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)
+    };
+
+    let mut a = -window_s;
+    let mut b = window_s;
+    let mut c = b - PHI * (b - a);
+    let mut d = a + PHI * (b - a);
+    let mut fc = eval(c)?;
+    let mut fd = eval(d)?;
+    let mut history = vec![(c, fc), (d, fd)];
+
+    let mut iters = 0;
+    while (b - a).abs() > tol_s && iters < MAX_ITERS {
+        if fc < fd {
+            b = d;
+            d = c;
+            fd = fc;
+            c = b - PHI * (b - a);
+            fc = eval(c)?;
+            history.push((c, fc));
+        } else {
+            a = c;
+            c = d;
+            fc = fd;
+            d = a + PHI * (b - a);
+            fd = eval(d)?;
+            history.push((d, fd));
         }
+        iters += 1;
     }
-    
-    // Get azimuth for the best time
-    let (sat_topo_best, body_topo, _, _) = compute_topo_vectors(elements, best_time, observer_ecef, observer_lat_rad, observer_lon_rad, body)?;
-    let jd_best = datetime_to_jd(best_time);
-    let gmst = gmst_rad(jd_best);
+
+    let (mut best_offset, mut best_sep) = if fc < fd { (c, fc) } else { (d, fd) };
+
+    // Parabolic polish: fit a parabola through the last three evaluated
+    // points and jump to its vertex for sub-millisecond convergence.
+    if history.len() >= 3 {
+        let n = history.len();
+        let (x1, y1) = history[n - 3];
+        let (x2, y2) = history[n - 2];
+        let (x3, y3) = history[n - 1];
+        let denom = (x1 - x2) * (x1 - x3) * (x2 - x3);
+        if denom.abs() > 1e-12 {
+            let a_coef = (x3 * (y2 - y1) + x2 * (y1 - y3) + x1 * (y3 - y2)) / denom;
+            let b_coef = (x3 * x3 * (y1 - y2) + x2 * x2 * (y3 - y1) + x1 * x1 * (y2 - y3)) / denom;
+            if a_coef > 0.0 {
+                let vertex = -b_coef / (2.0 * a_coef);
+                if vertex >= -window_s && vertex <= window_s {
+                    if let Ok(f_vertex) = eval(vertex) {
+                        if f_vertex < best_sep {
+                            best_offset = vertex;
+                            best_sep = f_vertex;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let _ = best_sep;
+    Ok(best_offset)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn refine_minimum(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    t_center: DateTime<Utc>,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    window_s: f64,
+    step_s: f64,
+    refraction: &RefractionParams,
+) -> Result<(DateTime<Utc>, f64, f64, f64, f64, f64, f64), String> {
+    // `step_s` is interpreted as the golden-section bracket convergence
+    // tolerance in seconds, not a sampling step.
+    let best_offset_s = golden_section_minimize(
+        sat, ephemeris, t_center, window_s, step_s, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction,
+    )?;
+    let best_time = t_center + Duration::milliseconds((best_offset_s * 1000.0) as i64);
+
+    let (sat_topo_best, body_topo, best_sat_alt, best_body_alt) =
+        compute_topo_vectors(sat, ephemeris, best_time, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
+    let min_sep = angle_between(&sat_topo_best, &body_topo);
+    let best_sat_range = sat_topo_best.norm();
+
+    let jd_best_ut1 = datetime_to_jd(ut1_from_utc(best_time));
+    let gmst = gmst_rad(jd_best_ut1);
     let rot_inv = rot_z(-gmst);
     let sat_topo_ecef = mat_mul_vec(&rot_inv, &sat_topo_best);
     let sat_topo_sez = ecef_to_sez(&sat_topo_ecef, observer_lat_rad, observer_lon_rad);
     let (_, sat_az) = altaz(&sat_topo_sez);
 
     let body_distance = body_topo.norm();
-    let body_radius_km = match body {
-        "Sun" => SUN_RADIUS_KM,
-        "Moon" => MOON_RADIUS_KM,
-        _ => return Err(format!("Unknown body: {}", body)),
-    };
+    let body_radius_km = body_mean_radius_km(body)?;
     let body_radius_rad = (body_radius_km / body_distance).asin();
     
     Ok((
@@ -382,20 +1542,23 @@ fn refine_minimum(
     ))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn calculate_speed_and_duration(
-    elements: &sgp4::Elements,
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
     t_min: DateTime<Utc>,
     observer_ecef: &Vector3,
     observer_lat_rad: f64,
     observer_lon_rad: f64,
     body: &str,
     step_s: f64,
+    refraction: &RefractionParams,
 ) -> Result<(f64, f64, f64, f64), String> {
     let t_minus = t_min - Duration::milliseconds((step_s * 1000.0) as i64);
     let t_plus = t_min + Duration::milliseconds((step_s * 1000.0) as i64);
-    
-    let (sat_m, _, _, _) = compute_topo_vectors(elements, t_minus, observer_ecef, observer_lat_rad, observer_lon_rad, body)?;
-    let (sat_p, _, _, _) = compute_topo_vectors(elements, t_plus, observer_ecef, observer_lat_rad, observer_lon_rad, body)?;
+
+    let (sat_m, _, _, _) = compute_topo_vectors(sat, ephemeris, t_minus, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
+    let (sat_p, _, _, _) = compute_topo_vectors(sat, ephemeris, t_plus, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
     
     let (alt_m, az_m) = altaz(&sat_m);
     let (alt_p, az_p) = altaz(&sat_p);
@@ -457,162 +1620,1739 @@ fn calculate_transit_duration(
     chord_length_deg / speed_deg_per_s
 }
 
+/// One local minimum of topocentric angular separation found by
+/// `refine_transit`. `entry_time`/`exit_time` are the instants where the
+/// satellite's disk starts/stops overlapping the body's, found by
+/// bisection; they are `None` when the minimum never reaches the
+/// combined angular radius, in which case `miss_distance_deg` holds how
+/// far short it fell.
+struct TransitRefinement {
+    t_closest: DateTime<Utc>,
+    min_separation_deg: f64,
+    entry_time: Option<DateTime<Utc>>,
+    exit_time: Option<DateTime<Utc>>,
+    miss_distance_deg: Option<f64>,
+}
+
+/// Bisects for the instant within `[t_lo, t_hi]` where the topocentric
+/// separation crosses `target_deg`, assuming the two endpoints straddle
+/// the crossing. If they don't (the crossing lies outside this bracket,
+/// e.g. the pass is already inside the disk at the window edge) the
+/// endpoint closer to `target_deg` is returned as a clamped estimate.
+#[allow(clippy::too_many_arguments)]
+fn bisect_separation_crossing(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    mut t_lo: DateTime<Utc>,
+    mut t_hi: DateTime<Utc>,
+    target_deg: f64,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    refraction: &RefractionParams,
+) -> Result<DateTime<Utc>, String> {
+    let sep_at = |t: DateTime<Utc>| -> Result<f64, String> {
+        let (sat_topo, body_topo, _, _) =
+            compute_topo_vectors(sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
+        Ok(angle_between(&sat_topo, &body_topo).to_degrees())
+    };
+
+    let mut f_lo = sep_at(t_lo)? - target_deg;
+    let f_hi = sep_at(t_hi)? - target_deg;
+    if f_lo == 0.0 {
+        return Ok(t_lo);
+    }
+    if f_lo.signum() == f_hi.signum() {
+        return Ok(if f_lo.abs() < f_hi.abs() { t_lo } else { t_hi });
+    }
+
+    const MAX_ITERS: u32 = 60;
+    for _ in 0..MAX_ITERS {
+        if (t_hi - t_lo).num_milliseconds().abs() < 1 {
+            break;
+        }
+        let t_mid = t_lo + (t_hi - t_lo) / 2;
+        let f_mid = sep_at(t_mid)? - target_deg;
+        if f_mid == 0.0 {
+            return Ok(t_mid);
+        }
+        if f_mid.signum() == f_lo.signum() {
+            t_lo = t_mid;
+            f_lo = f_mid;
+        } else {
+            t_hi = t_mid;
+        }
+    }
+    Ok(t_lo + (t_hi - t_lo) / 2)
+}
+
+/// Scans `[t_start, t_end]` on a 1 s grid for local minima of the
+/// topocentric angular separation between the satellite and `body`,
+/// refines each with `golden_section_minimize`, and locates the true
+/// disk entry/exit instants by bisection rather than assuming a constant
+/// angular rate. Unlike `calculate_transit_duration`, entry and exit need
+/// not be symmetric around the minimum, since the satellite's apparent
+/// motion actually varies across the pass.
+#[allow(clippy::too_many_arguments)]
+fn refine_transit(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    t_start: DateTime<Utc>,
+    t_end: DateTime<Utc>,
+    refraction: &RefractionParams,
+) -> Result<Vec<TransitRefinement>, String> {
+    const COARSE_STEP_S: f64 = 1.0;
+    const REFINE_TOL_S: f64 = 0.0005;
+
+    let total_s = (t_end - t_start).num_milliseconds() as f64 / 1000.0;
+    if total_s <= 0.0 {
+        return Ok(Vec::new());
+    }
+    let n_steps = (total_s / COARSE_STEP_S).ceil() as i64;
+
+    let mut samples = Vec::with_capacity(n_steps as usize + 1);
+    for i in 0..=n_steps {
+        let t = t_start + Duration::milliseconds((i as f64 * COARSE_STEP_S * 1000.0) as i64);
+        let (sat_topo, body_topo, _, _) =
+            compute_topo_vectors(sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction)?;
+        samples.push((t, angle_between(&sat_topo, &body_topo).to_degrees()));
+    }
+
+    let mut refinements = Vec::new();
+    for i in 1..samples.len() - 1 {
+        let (t_prev, sep_prev) = samples[i - 1];
+        let (t_mid, sep_mid) = samples[i];
+        let (t_next, sep_next) = samples[i + 1];
+
+        // A local minimum sits where the first difference changes sign:
+        // still descending on one side, ascending on the other.
+        if !(sep_mid <= sep_prev && sep_mid <= sep_next) {
+            continue;
+        }
+
+        let window_s = (t_mid - t_prev).num_milliseconds() as f64 / 1000.0;
+        let offset_s = golden_section_minimize(
+            sat, ephemeris, t_mid, window_s, REFINE_TOL_S,
+            observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction,
+        )?;
+        let t_closest = t_mid + Duration::milliseconds((offset_s * 1000.0) as i64);
+
+        let (sat_topo, body_topo, _, _) = compute_topo_vectors(
+            sat, ephemeris, t_closest, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction,
+        )?;
+        let min_separation_deg = angle_between(&sat_topo, &body_topo).to_degrees();
+
+        let sat_disk_radius_deg = ((ISS_DIMENSION_M / 1000.0 / 2.0) / sat_topo.norm()).asin().to_degrees();
+        let body_radius_km = body_mean_radius_km(body)?;
+        let body_disk_radius_deg = (body_radius_km / body_topo.norm()).asin().to_degrees();
+        let combined_radius_deg = sat_disk_radius_deg + body_disk_radius_deg;
+
+        if min_separation_deg > combined_radius_deg {
+            refinements.push(TransitRefinement {
+                t_closest,
+                min_separation_deg,
+                entry_time: None,
+                exit_time: None,
+                miss_distance_deg: Some(min_separation_deg - combined_radius_deg),
+            });
+            continue;
+        }
+
+        let entry_time = bisect_separation_crossing(
+            sat, ephemeris, t_prev, t_closest, combined_radius_deg,
+            observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction,
+        )?;
+        let exit_time = bisect_separation_crossing(
+            sat, ephemeris, t_closest, t_next, combined_radius_deg,
+            observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction,
+        )?;
+
+        refinements.push(TransitRefinement {
+            t_closest,
+            min_separation_deg,
+            entry_time: Some(entry_time),
+            exit_time: Some(exit_time),
+            miss_distance_deg: None,
+        });
+    }
+
+    Ok(refinements)
+}
+
+/// Transit centerline ground track: for each `step_s` sample across
+/// `[t0, t1]`, casts the ray from `body` through the satellite's ECEF
+/// position, takes its near intersection with the WGS-84 ellipsoid
+/// (`line_ellipsoid_intersection`), and converts the hit point back to
+/// geodetic lat/lon — the curve along which the satellite appears
+/// dead-center on the body's disk. `half_width_km` is the combined
+/// angular radius (satellite + body), mapped to ground distance via the
+/// satellite's actual range from that ground point, delimiting the
+/// visibility corridor on either side of the centerline. Samples where
+/// the ray misses the ellipsoid (the transit isn't visible from
+/// anywhere on Earth at that instant) are skipped.
+fn transit_ground_track(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    body: &str,
+    t0: DateTime<Utc>,
+    t1: DateTime<Utc>,
+    step_s: f64,
+) -> Vec<GroundTrackPoint> {
+    let total_s = (t1 - t0).num_milliseconds() as f64 / 1000.0;
+    if total_s <= 0.0 || step_s <= 0.0 {
+        return Vec::new();
+    }
+    let n_steps = (total_s / step_s) as i64;
+
+    let mut points = Vec::new();
+    for i in 0..=n_steps {
+        let t = t0 + Duration::milliseconds((i as f64 * step_s * 1000.0) as i64);
+        let jd_tt = datetime_to_jd(tt_from_utc(t));
+        let jd_ut1 = datetime_to_jd(ut1_from_utc(t));
+
+        let sat_teme = match sat.position_teme(t) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+        let body_eci_meandate = match body_position_eci_via(ephemeris, jd_tt, body) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let body_eci = mat_mul_vec(&j2000_to_teme_matrix(jd_tt), &body_eci_meandate);
+
+        let gmst = gmst_rad(jd_ut1);
+        let rot_inv = rot_z(-gmst);
+        let sat_ecef = mat_mul_vec(&rot_inv, &sat_teme);
+        let body_ecef = mat_mul_vec(&rot_inv, &body_eci);
+
+        let body_radius_km = match body_mean_radius_km(body) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        let body_angular_radius_rad = (body_radius_km / body_ecef.norm()).asin();
+
+        let Some(hit) = line_ellipsoid_intersection(&body_ecef, &sat_ecef) else {
+            continue;
+        };
+        let (lat_deg, lon_deg) = ecef_to_geodetic(&hit);
+
+        let sat_range_km = sat_ecef.sub(&hit).norm();
+        let sat_angular_radius_rad = ((ISS_DIMENSION_M / 1000.0) / 2.0 / sat_range_km).atan();
+        let half_width_km = sat_range_km * (body_angular_radius_rad + sat_angular_radius_rad);
+
+        points.push(GroundTrackPoint {
+            time_utc: t.to_rfc3339(),
+            lat_deg,
+            lon_deg,
+            half_width_km,
+        });
+    }
+
+    points
+}
+
 // ============================================================================
-// Main Prediction Function (FFI)
+// Solar Eclipse Local Circumstances
 // ============================================================================
 
+/// Local circumstances of a solar eclipse (the Moon occulting the Sun) for
+/// an observer: first/last contact (disk-edge crossing), the time of
+/// greatest eclipse, magnitude, obscuration, and event type. Contact times
+/// are `None` when the search window only covers part of the event (the
+/// Sun/Moon are still overlapping at a window edge).
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+struct EclipseCircumstances {
+    first_contact_utc: Option<String>,
+    max_eclipse_utc: String,
+    last_contact_utc: Option<String>,
+    separation_arcmin: f64,
+    sun_radius_arcmin: f64,
+    moon_radius_arcmin: f64,
+    magnitude: f64,
+    obscuration: f64,
+    kind: String,
+}
+
+/// Topocentric Sun and Moon position vectors (TEME-equivalent,
+/// epoch-of-date equatorial) for an observer, mirroring
+/// `compute_topo_vectors` but for two celestial bodies rather than a
+/// satellite and a body.
+fn sun_moon_topo_vectors(
+    dt: DateTime<Utc>,
+    observer_ecef: &Vector3,
+    ephemeris: &dyn Ephemeris,
+) -> (Vector3, Vector3) {
+    let jd_tt = datetime_to_jd(tt_from_utc(dt));
+    let jd_ut1 = datetime_to_jd(ut1_from_utc(dt));
+    let gmst = gmst_rad(jd_ut1);
+    let observer_teme = mat_mul_vec(&rot_z(gmst), observer_ecef);
+
+    let precession_nutation = j2000_to_teme_matrix(jd_tt);
+    let sun_eci = mat_mul_vec(&precession_nutation, &ephemeris.sun_eci(jd_tt));
+    let moon_eci = mat_mul_vec(&precession_nutation, &ephemeris.moon_eci(jd_tt));
+
+    (topocentric(&sun_eci, &observer_teme), topocentric(&moon_eci, &observer_teme))
+}
+
+/// Topocentric Sun-Moon angular separation (deg) at `dt`.
+fn sun_moon_separation_deg(dt: DateTime<Utc>, observer_ecef: &Vector3, ephemeris: &dyn Ephemeris) -> f64 {
+    let (sun_topo, moon_topo) = sun_moon_topo_vectors(dt, observer_ecef, ephemeris);
+    angle_between(&sun_topo, &moon_topo).to_degrees()
+}
+
+/// Golden-section search for the minimum Sun-Moon separation inside
+/// `[-window_s, window_s]` around `t_center`, returning the offset in
+/// fractional seconds. Mirrors `golden_section_minimize`, without the
+/// final parabolic polish (the eclipse window is coarse enough that
+/// sub-millisecond precision on the time of greatest eclipse isn't
+/// meaningful).
+fn golden_section_minimize_sun_moon(
+    t_center: DateTime<Utc>,
+    window_s: f64,
+    tol_s: f64,
+    observer_ecef: &Vector3,
+    ephemeris: &dyn Ephemeris,
+) -> f64 {
+    const PHI: f64 = 0.618_033_988_749_895;
+    const MAX_ITERS: u32 = 100;
+
+    let eval = |offset_s: f64| {
+        let t = t_center + Duration::milliseconds((offset_s * 1000.0) as i64);
+        sun_moon_separation_deg(t, observer_ecef, ephemeris)
+    };
+
+    let mut a = -window_s;
+    let mut b = window_s;
+    let mut c = b - PHI * (b - a);
+    let mut d = a + PHI * (b - a);
+    let mut fc = eval(c);
+    let mut fd = eval(d);
+
+    let mut iters = 0;
+    while (b - a).abs() > tol_s && iters < MAX_ITERS {
+        if fc < fd {
+            b = d;
+            d = c;
+            fd = fc;
+            c = b - PHI * (b - a);
+            fc = eval(c);
+        } else {
+            a = c;
+            c = d;
+            fc = fd;
+            d = a + PHI * (b - a);
+            fd = eval(d);
+        }
+        iters += 1;
+    }
+
+    if fc < fd { c } else { d }
+}
+
+/// Bisects for the instant within `[t_lo, t_hi]` where the Sun-Moon
+/// separation crosses `target_deg`, assuming the two endpoints straddle
+/// the crossing. Mirrors `bisect_separation_crossing`.
+fn bisect_sun_moon_crossing(
+    mut t_lo: DateTime<Utc>,
+    mut t_hi: DateTime<Utc>,
+    target_deg: f64,
+    observer_ecef: &Vector3,
+    ephemeris: &dyn Ephemeris,
+) -> Option<DateTime<Utc>> {
+    let sep_at = |t: DateTime<Utc>| sun_moon_separation_deg(t, observer_ecef, ephemeris) - target_deg;
+
+    let mut f_lo = sep_at(t_lo);
+    let f_hi = sep_at(t_hi);
+    if f_lo.signum() == f_hi.signum() {
+        return None;
+    }
+
+    const MAX_ITERS: u32 = 60;
+    for _ in 0..MAX_ITERS {
+        if (t_hi - t_lo).num_milliseconds().abs() < 1 {
+            break;
+        }
+        let t_mid = t_lo + (t_hi - t_lo) / 2;
+        let f_mid = sep_at(t_mid);
+        if f_mid == 0.0 {
+            return Some(t_mid);
+        }
+        if f_mid.signum() == f_lo.signum() {
+            t_lo = t_mid;
+            f_lo = f_mid;
+        } else {
+            t_hi = t_mid;
+        }
+    }
+    Some(t_lo + (t_hi - t_lo) / 2)
+}
+
+/// Fraction of the Sun's disk area covered by the Moon's disk, given their
+/// angular radii and center separation (all in the same angular unit).
+/// Standard circle-circle intersection area, normalized by the Sun's disk
+/// area.
+fn circle_overlap_fraction(sun_radius: f64, moon_radius: f64, separation: f64) -> f64 {
+    if separation >= sun_radius + moon_radius {
+        return 0.0;
+    }
+    if separation <= (sun_radius - moon_radius).abs() {
+        let covering_radius = sun_radius.min(moon_radius);
+        return (covering_radius * covering_radius) / (sun_radius * sun_radius);
+    }
+
+    let d = separation;
+    let r1 = sun_radius;
+    let r2 = moon_radius;
+    let part1 = r1 * r1 * ((d * d + r1 * r1 - r2 * r2) / (2.0 * d * r1)).clamp(-1.0, 1.0).acos();
+    let part2 = r2 * r2 * ((d * d + r2 * r2 - r1 * r1) / (2.0 * d * r2)).clamp(-1.0, 1.0).acos();
+    let triangle_term =
+        0.5 * ((-d + r1 + r2) * (d + r1 - r2) * (d - r1 + r2) * (d + r1 + r2)).max(0.0).sqrt();
+    let overlap_area = part1 + part2 - triangle_term;
+
+    (overlap_area / (PI * r1 * r1)).clamp(0.0, 1.0)
+}
+
+/// Searches `[t_start, t_end]` for a solar eclipse visible from
+/// `observer_ecef`: scans on a 1-minute grid for the instant of smallest
+/// Sun-Moon separation, refines it by golden-section search, and — if the
+/// disks overlap at all — bisects for first/last contact on either side.
+/// Returns `Ok(None)` if no eclipse (however partial) occurs in the
+/// window. The Sun's altitude is not checked, so an eclipse that happens
+/// while the Sun is below the observer's horizon is still reported.
+fn eclipse_local_circumstances(
+    observer_ecef: &Vector3,
+    t_start: DateTime<Utc>,
+    t_end: DateTime<Utc>,
+    ephemeris: &dyn Ephemeris,
+) -> Result<Option<EclipseCircumstances>, String> {
+    const COARSE_STEP_S: f64 = 60.0;
+    const REFINE_TOL_S: f64 = 0.01;
+
+    let total_s = (t_end - t_start).num_milliseconds() as f64 / 1000.0;
+    if total_s <= 0.0 {
+        return Ok(None);
+    }
+    let n_steps = (total_s / COARSE_STEP_S).ceil() as i64;
+
+    let mut best_t = t_start;
+    let mut best_sep = f64::MAX;
+    for i in 0..=n_steps {
+        let t = t_start + Duration::milliseconds((i as f64 * COARSE_STEP_S * 1000.0) as i64);
+        let sep = sun_moon_separation_deg(t, observer_ecef, ephemeris);
+        if sep < best_sep {
+            best_sep = sep;
+            best_t = t;
+        }
+    }
+
+    let offset_s = golden_section_minimize_sun_moon(best_t, COARSE_STEP_S, REFINE_TOL_S, observer_ecef, ephemeris);
+    let t_max = best_t + Duration::milliseconds((offset_s * 1000.0) as i64);
+
+    let (sun_topo, moon_topo) = sun_moon_topo_vectors(t_max, observer_ecef, ephemeris);
+    let separation_deg = angle_between(&sun_topo, &moon_topo).to_degrees();
+    let sun_radius_deg = (SUN_RADIUS_KM / sun_topo.norm()).asin().to_degrees();
+    let moon_radius_deg = (MOON_RADIUS_KM / moon_topo.norm()).asin().to_degrees();
+
+    if separation_deg > sun_radius_deg + moon_radius_deg {
+        return Ok(None);
+    }
+
+    let magnitude = (sun_radius_deg + moon_radius_deg - separation_deg) / (2.0 * sun_radius_deg);
+    let obscuration = circle_overlap_fraction(sun_radius_deg, moon_radius_deg, separation_deg);
+
+    let kind = if separation_deg + sun_radius_deg < moon_radius_deg {
+        "total"
+    } else if moon_radius_deg < sun_radius_deg && separation_deg + moon_radius_deg < sun_radius_deg {
+        "annular"
+    } else {
+        "partial"
+    };
+
+    let combined_radius_deg = sun_radius_deg + moon_radius_deg;
+    let first_contact = bisect_sun_moon_crossing(t_start, t_max, combined_radius_deg, observer_ecef, ephemeris);
+    let last_contact = bisect_sun_moon_crossing(t_max, t_end, combined_radius_deg, observer_ecef, ephemeris);
+
+    Ok(Some(EclipseCircumstances {
+        first_contact_utc: first_contact.map(|t| t.to_rfc3339()),
+        max_eclipse_utc: t_max.to_rfc3339(),
+        last_contact_utc: last_contact.map(|t| t.to_rfc3339()),
+        separation_arcmin: separation_deg * 60.0,
+        sun_radius_arcmin: sun_radius_deg * 60.0,
+        moon_radius_arcmin: moon_radius_deg * 60.0,
+        magnitude,
+        obscuration,
+        kind: kind.to_string(),
+    }))
+}
+
+/// Local circumstances of the solar eclipse (if any) visible from
+/// `lat`/`lon`/`alt_m` within `[start_epoch, end_epoch]` (Unix seconds).
+/// `spk_kernel_data`/`spk_kernel_len` are an optional (may be null/0) JPL
+/// SPK (.bsp) kernel, e.g. DE440, for arcsecond-level Sun/Moon positions;
+/// without one, Sun/Moon use the dependency-free analytic series. Returns
+/// a JSON object on success, or the literal `null` if no eclipse occurs in
+/// the window; the returned pointer must be freed with `free_json`. The
+/// Sun's altitude is not checked, so an eclipse below the observer's
+/// horizon is still reported.
+///
+/// # Safety
+/// `spk_kernel_data` must point to at least `spk_kernel_len` readable bytes
+/// when non-null.
 #[no_mangle]
-pub extern "C" fn predict_transits(
-    tle1: *const c_char,
-    tle2: *const c_char,
+pub extern "C" fn predict_eclipse(
     lat: f64,
     lon: f64,
     alt_m: f64,
     start_epoch: i64,
     end_epoch: i64,
-    max_distance_km: f64,
+    spk_kernel_data: *const u8,
+    spk_kernel_len: usize,
 ) -> *mut c_char {
     init_logger();
-    
-    info!("ISS Transit Prediction starting");
-    info!("  Location: {:.5}°N, {:.5}°E, {}m", lat, lon, alt_m);
-    
-    let tle1_str = unsafe { CStr::from_ptr(tle1) }.to_string_lossy().into_owned();
-    let tle2_str = unsafe { CStr::from_ptr(tle2) }.to_string_lossy().into_owned();
-    
-    let elements = match sgp4::Elements::from_tle(
-        Some("ISS".to_string()),
-        tle1_str.as_bytes(),
-        tle2_str.as_bytes(),
-    ) {
-        Ok(e) => e,
-        Err(e) => {
-            warn!("TLE parse error: {}", e);
-            return CString::new("[]").unwrap().into_raw();
-        }
-    };
-    
+
     let start = DateTime::<Utc>::from_timestamp(start_epoch, 0).unwrap();
     let end = DateTime::<Utc>::from_timestamp(end_epoch, 0).unwrap();
-    
-    info!("  Time: {} to {}", start, end);
-    info!("  Duration: {} days", (end - start).num_days());
-    
     let observer_ecef = geodetic_to_ecef(lat.to_radians(), lon.to_radians(), alt_m);
-    let observer_lat_rad = lat.to_radians();
-    let observer_lon_rad = lon.to_radians();
-    
-    // Search parameters (same as main.rs defaults)
-    let coarse_step_s = 20.0;
-    let fine_step_s = 1.0;
-    let refine_window_s = 60.0;
-    let alt_min = 5.0;
-    let near_margin_deg = 0.5;
-    
-    let mut events = Vec::new();
-    let mut t = start;
-    
-    // DIRECT SCANNING ALGORITHM (same as main.rs)
-    // No pass pre-filtering - scans every 20s checking for close approaches
-    while t <= end {
-        for body in ["Sun", "Moon"] {
-            match compute_topo_vectors(&elements, t, &observer_ecef, observer_lat_rad, observer_lon_rad, body) {
-                Ok((sat_topo, body_topo, sat_alt, body_alt)) => {
-                    if sat_alt < alt_min || body_alt < 0.0 {
-                        continue;
-                    }
-                    
-                    let sep = angle_between(&sat_topo, &body_topo).to_degrees();
-                    let body_distance = body_topo.norm();
-                    let body_radius_km = match body {
-                        "Sun" => SUN_RADIUS_KM,
-                        "Moon" => MOON_RADIUS_KM,
-                        _ => continue,
-                    };
-                    let body_radius_deg = (body_radius_km / body_distance).asin().to_degrees();
-                    
-                    if sep <= body_radius_deg + near_margin_deg + 2.0 {
-                        match refine_minimum(&elements, t, &observer_ecef, observer_lat_rad, observer_lon_rad, body, refine_window_s, fine_step_s) {
-                            Ok((t_min, min_sep_deg, radius_deg, sat_alt_refined, sat_az_refined, body_alt_refined, sat_range)) => {
-                                let mut kind = if min_sep_deg <= radius_deg {
-                                    "transit"
-                                } else if min_sep_deg <= radius_deg + near_margin_deg {
-                                    "near"
-                                } else {
-                                    ""
-                                };
-                                
-                                // Check if event is "reachable" (within travel distance)
-                                if kind.is_empty() && sat_range > 0.0 && max_distance_km > 0.0 {
-                                    // Calculate ground distance needed to travel to see the transit
-                                    // Using small angle approximation: arc_length ≈ angle_rad × distance
-                                    let required_travel_km = min_sep_deg.to_radians() * sat_range;
-                                    if required_travel_km <= max_distance_km && body_alt_refined >= 0.0 {
-                                        kind = "reachable";
-                                    }
-                                }
-                                
-                                if kind.is_empty() {
-                                    continue;
-                                }
-                                
-                                let (speed_deg_per_s, velocity_alt_deg_per_s, velocity_az_deg_per_s, motion_direction_deg) = calculate_speed_and_duration(
-                                    &elements, t_min, &observer_ecef, observer_lat_rad, observer_lon_rad, body, fine_step_s
-                                ).unwrap_or((0.0, 0.0, 0.0, 0.0));
-                                
-                                let duration_s = calculate_transit_duration(min_sep_deg, radius_deg, speed_deg_per_s);
-                                
-                                let sat_ang_size = if sat_range > 0.0 {
-                                    let size_km = ISS_DIMENSION_M / 1000.0;
-                                    (size_km / sat_range).to_degrees() * 3600.0
-                                } else {
-                                    0.0
-                                };
-                                
-                                events.push(Event {
-                                    time_utc: t_min.to_rfc3339(),
-                                    body: body.to_string(),
-                                    separation_arcmin: min_sep_deg * 60.0,
-                                    target_radius_arcmin: radius_deg * 60.0,
-                                    kind: kind.to_string(),
-                                    sat_alt_deg: sat_alt_refined,
-                                    sat_az_deg: sat_az_refined,
-                                    target_alt_deg: body_alt_refined,
-                                    satellite: "ISS (ZARYA)".to_string(),
-                                    speed_deg_per_s,
-                                    speed_arcmin_per_s: speed_deg_per_s * 60.0,
-                                    velocity_alt_deg_per_s,
-                                    velocity_az_deg_per_s,
-                                    motion_direction_deg,
-                                    duration_s,
-                                    sat_angular_size_arcsec: sat_ang_size,
-                                    sat_distance_km: sat_range,
-                                });
-                                
-                                info!("Event found: {} {} at {}", kind, body, t_min);
-                                t = t_min + Duration::seconds(300);
-                                break;
-                            }
-                            Err(e) => {
-                                warn!("Refinement error: {}", e);
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("Computation error at {}: {}", t, e);
-                }
+    let ephemeris_backend = resolve_ephemeris(spk_kernel_data, spk_kernel_len);
+    let ephemeris: &dyn Ephemeris = ephemeris_backend.as_ref();
+
+    let json = match eclipse_local_circumstances(&observer_ecef, start, end, ephemeris) {
+        Ok(Some(circumstances)) => serde_json::to_string(&circumstances).unwrap_or_else(|_| "null".to_string()),
+        Ok(None) => "null".to_string(),
+        Err(e) => {
+            warn!("Eclipse computation error: {}", e);
+            "null".to_string()
+        }
+    };
+
+    CString::new(json).unwrap().into_raw()
+}
+
+// ============================================================================
+// SP3 Precise Ephemeris (IGS orbit products, ECEF position/velocity)
+// ============================================================================
+
+const SP3_VELOCITY_UNIT_KM_S: f64 = 1.0e-4; // SP3 velocity records are tabulated in dm/s
+
+/// One tabulated SP3 epoch: ECEF position (km) and, when the file includes
+/// velocity records, ECEF velocity (km/s).
+#[derive(Debug, Clone, Copy)]
+struct Sp3Epoch {
+    position_km: Vector3,
+    velocity_km_s: Option<Vector3>,
+}
+
+/// A parsed SP3 (a/b/c/d) orbit file: ECEF positions, and velocities when
+/// present, for a single vehicle across its tabulated epochs. Records for
+/// any other vehicle ID in a multi-satellite file are ignored, since this
+/// feeds a single-satellite transit search.
+struct Sp3Ephemeris {
+    epochs: BTreeMap<i64, Sp3Epoch>, // key: epoch, milliseconds since Unix epoch
+}
+
+/// SP3 marks an unavailable position either as all-zero or as the
+/// `999999.999999` sentinel.
+fn is_sp3_position_sentinel(x: f64, y: f64, z: f64) -> bool {
+    (x == 0.0 && y == 0.0 && z == 0.0) || x.abs() >= 99999.0 || y.abs() >= 99999.0 || z.abs() >= 99999.0
+}
+
+/// Parses a `*  YYYY MM DD HH MM SS.SSSSSSSS` SP3 epoch header line into
+/// milliseconds since the Unix epoch.
+fn parse_sp3_epoch_line(line: &str) -> Result<i64, String> {
+    let fields: Vec<&str> = line[1..].split_whitespace().collect();
+    if fields.len() < 6 {
+        return Err(format!("malformed SP3 epoch line: {}", line));
+    }
+    let parse_field = |s: &str, what: &str| s.parse::<f64>().map_err(|_| format!("bad SP3 epoch {}: {}", what, line));
+    let year = parse_field(fields[0], "year")? as i32;
+    let month = parse_field(fields[1], "month")? as u32;
+    let day = parse_field(fields[2], "day")? as u32;
+    let hour = parse_field(fields[3], "hour")? as u32;
+    let minute = parse_field(fields[4], "minute")? as u32;
+    let second = parse_field(fields[5], "second")?;
+
+    let date = NaiveDate::from_ymd_opt(year, month, day)
+        .ok_or_else(|| format!("invalid SP3 epoch date: {}", line))?;
+    let time = NaiveTime::from_hms_milli_opt(hour, minute, second.trunc() as u32, (second.fract() * 1000.0).round() as u32)
+        .ok_or_else(|| format!("invalid SP3 epoch time: {}", line))?;
+    Ok(DateTime::<Utc>::from_naive_utc_and_offset(date.and_time(time), Utc).timestamp_millis())
+}
+
+/// Hermite interpolation through `xs`/`ys`/`dys`, matching both value and
+/// derivative at every node. Built from Newton's divided-difference form
+/// with each node doubled, the standard construction for value+derivative
+/// interpolants.
+fn hermite_interpolate(xs: &[f64], ys: &[f64], dys: &[f64], x: f64) -> f64 {
+    let n = xs.len();
+    let m = 2 * n;
+    let mut z = vec![0.0; m];
+    let mut q = vec![vec![0.0; m]; m];
+
+    for i in 0..n {
+        z[2 * i] = xs[i];
+        z[2 * i + 1] = xs[i];
+        q[2 * i][0] = ys[i];
+        q[2 * i + 1][0] = ys[i];
+        q[2 * i + 1][1] = dys[i];
+        if i != 0 {
+            q[2 * i][1] = (q[2 * i][0] - q[2 * i - 1][0]) / (z[2 * i] - z[2 * i - 1]);
+        }
+    }
+    for j in 2..m {
+        for i in j..m {
+            q[i][j] = (q[i][j - 1] - q[i - 1][j - 1]) / (z[i] - z[i - j]);
+        }
+    }
+
+    let mut result = q[0][0];
+    let mut product = 1.0;
+    for i in 1..m {
+        product *= x - z[i - 1];
+        result += q[i][i] * product;
+    }
+    result
+}
+
+/// Lagrange interpolation (position-only, no derivative matching) through
+/// `xs`/`ys`, used when the tabulated points lack velocity records.
+fn lagrange_interpolate(xs: &[f64], ys: &[f64], x: f64) -> f64 {
+    let n = xs.len();
+    let mut result = 0.0;
+    for i in 0..n {
+        let mut term = ys[i];
+        for (j, xj) in xs.iter().enumerate() {
+            if i != j {
+                term *= (x - xj) / (xs[i] - xj);
+            }
+        }
+        result += term;
+    }
+    result
+}
+
+impl Sp3Ephemeris {
+    /// Parses an SP3 file already read into memory, keeping only records
+    /// for the first vehicle ID encountered in a `P`/`V` line and skipping
+    /// `999999.`/all-zero sentinel positions.
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut epochs: BTreeMap<i64, Sp3Epoch> = BTreeMap::new();
+        let mut target_id: Option<String> = None;
+        let mut current_epoch_ms: Option<i64> = None;
+        let mut pending_position: Option<Vector3> = None;
+
+        for line in text.lines() {
+            if line.starts_with('*') {
+                if let (Some(ms), Some(pos)) = (current_epoch_ms, pending_position.take()) {
+                    epochs.entry(ms).or_insert(Sp3Epoch { position_km: pos, velocity_km_s: None });
+                }
+                current_epoch_ms = Some(parse_sp3_epoch_line(line)?);
+                continue;
+            }
+            if line.len() < 4 || !(line.starts_with('P') || line.starts_with('V')) {
+                continue;
+            }
+            let id = line[1..4].trim();
+            if id.is_empty() {
+                continue;
+            }
+            if target_id.is_none() {
+                target_id = Some(id.to_string());
+            }
+            if target_id.as_deref() != Some(id) {
+                continue; // a different vehicle's record in a multi-satellite file
+            }
+            let Some(ms) = current_epoch_ms else { continue };
+            let fields: Vec<f64> = line[4..].split_whitespace().filter_map(|s| s.parse().ok()).collect();
+            if fields.len() < 3 {
+                continue;
+            }
+
+            if line.starts_with('P') {
+                if is_sp3_position_sentinel(fields[0], fields[1], fields[2]) {
+                    continue;
+                }
+                pending_position = Some(Vector3::new(fields[0], fields[1], fields[2]));
+            } else {
+                let Some(pos) = pending_position.take() else { continue };
+                let velocity_km_s = Some(Vector3::new(
+                    fields[0] * SP3_VELOCITY_UNIT_KM_S,
+                    fields[1] * SP3_VELOCITY_UNIT_KM_S,
+                    fields[2] * SP3_VELOCITY_UNIT_KM_S,
+                ));
+                epochs.insert(ms, Sp3Epoch { position_km: pos, velocity_km_s });
+            }
+        }
+        if let (Some(ms), Some(pos)) = (current_epoch_ms, pending_position.take()) {
+            epochs.entry(ms).or_insert(Sp3Epoch { position_km: pos, velocity_km_s: None });
+        }
+
+        if epochs.is_empty() {
+            return Err("SP3 file contained no usable position records".to_string());
+        }
+        Ok(Self { epochs })
+    }
+
+    /// Interpolated ECEF position (km) at `dt`: Hermite (matching value and
+    /// derivative) over the 8 nearest tabulated points when all of them
+    /// carry a velocity record, otherwise Lagrange (position-only) over the
+    /// 10 nearest. Rejects query times outside the tabulated span.
+    fn interpolate_ecef(&self, dt: DateTime<Utc>) -> Result<Vector3, String> {
+        const HERMITE_WINDOW: usize = 8;
+        const LAGRANGE_WINDOW: usize = 10;
+
+        let query_ms = dt.timestamp_millis();
+        let first_ms = *self.epochs.keys().next().ok_or("SP3 file has no epochs")?;
+        let last_ms = *self.epochs.keys().next_back().expect("checked non-empty above");
+        if query_ms < first_ms || query_ms > last_ms {
+            return Err("query time is outside the SP3 file's tabulated span".to_string());
+        }
+
+        let mut nodes: Vec<(i64, Sp3Epoch)> = self.epochs.iter().map(|(ms, e)| (*ms, *e)).collect();
+        nodes.sort_by_key(|(ms, _)| (*ms - query_ms).abs());
+
+        let use_hermite = nodes.iter().take(HERMITE_WINDOW).all(|(_, e)| e.velocity_km_s.is_some());
+        let window = if use_hermite { HERMITE_WINDOW } else { LAGRANGE_WINDOW };
+        nodes.truncate(window.min(nodes.len()));
+        nodes.sort_by_key(|(ms, _)| *ms);
+
+        if nodes.len() < 2 {
+            return Err("not enough tabulated SP3 points near the query time".to_string());
+        }
+
+        let t0 = nodes[0].0;
+        let xs: Vec<f64> = nodes.iter().map(|(ms, _)| (*ms - t0) as f64 / 1000.0).collect();
+        let query_t = (query_ms - t0) as f64 / 1000.0;
+        let ys_x: Vec<f64> = nodes.iter().map(|(_, e)| e.position_km.x).collect();
+        let ys_y: Vec<f64> = nodes.iter().map(|(_, e)| e.position_km.y).collect();
+        let ys_z: Vec<f64> = nodes.iter().map(|(_, e)| e.position_km.z).collect();
+
+        if use_hermite {
+            let dys_x: Vec<f64> = nodes.iter().map(|(_, e)| e.velocity_km_s.unwrap().x).collect();
+            let dys_y: Vec<f64> = nodes.iter().map(|(_, e)| e.velocity_km_s.unwrap().y).collect();
+            let dys_z: Vec<f64> = nodes.iter().map(|(_, e)| e.velocity_km_s.unwrap().z).collect();
+            Ok(Vector3::new(
+                hermite_interpolate(&xs, &ys_x, &dys_x, query_t),
+                hermite_interpolate(&xs, &ys_y, &dys_y, query_t),
+                hermite_interpolate(&xs, &ys_z, &dys_z, query_t),
+            ))
+        } else {
+            Ok(Vector3::new(
+                lagrange_interpolate(&xs, &ys_x, query_t),
+                lagrange_interpolate(&xs, &ys_y, query_t),
+                lagrange_interpolate(&xs, &ys_z, query_t),
+            ))
+        }
+    }
+}
+
+/// Wraps a parsed SP3 file as a `SatellitePosition` source: the tabulated
+/// positions are already ECEF, so the only work is rotating them into
+/// TEME-of-date via the same GMST rotation used for the observer, letting
+/// the shared transit-detection pipeline stay ephemeris-agnostic.
+struct Sp3Satellite {
+    ephemeris: Sp3Ephemeris,
+}
+
+impl SatellitePosition for Sp3Satellite {
+    fn position_teme(&self, dt: DateTime<Utc>) -> Result<Vector3, String> {
+        let ecef = self.ephemeris.interpolate_ecef(dt)?;
+        let jd_ut1 = datetime_to_jd(ut1_from_utc(dt));
+        let gmst = gmst_rad(jd_ut1);
+        Ok(mat_mul_vec(&rot_z(gmst), &ecef))
+    }
+}
+
+// ============================================================================
+// Scheduling: Inclusion/Exclusion Windows and Elevation Mask
+// ============================================================================
+
+/// A closed UTC epoch interval (Unix seconds, inclusive both ends).
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+struct ScheduleWindow {
+    start_epoch: i64,
+    end_epoch: i64,
+}
+
+impl ScheduleWindow {
+    fn contains(&self, epoch_s: i64) -> bool {
+        epoch_s >= self.start_epoch && epoch_s <= self.end_epoch
+    }
+}
+
+/// A minimum-elevation override for satellite azimuths in
+/// `[az_start_deg, az_end_deg)`, for sites with a direction-dependent
+/// obstructed horizon (a building or ridge in one compass direction).
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+struct AzimuthMaskSector {
+    az_start_deg: f64,
+    az_end_deg: f64,
+    min_elevation_deg: f64,
+}
+
+impl AzimuthMaskSector {
+    fn contains(&self, az_deg: f64) -> bool {
+        az_deg >= self.az_start_deg && az_deg < self.az_end_deg
+    }
+}
+
+/// Observer-side scheduling constraints for `predict_transits`/
+/// `predict_transits_sp3`, modeled on a tracking scheduler: an event is
+/// reported only if its instant falls inside at least one inclusion
+/// window (or any time, if `inclusion_windows` is empty) and inside no
+/// exclusion window, and only if the satellite clears the elevation mask
+/// — `azimuth_mask` where it covers the satellite's azimuth, else
+/// `min_elevation_deg` — for at least `min_duration_s` around the event,
+/// sampled at least `min_samples` times. Deserialized from the optional
+/// `schedule_json` FFI argument; all fields default to "unconstrained"
+/// when omitted.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+struct SchedulingConfig {
+    inclusion_windows: Vec<ScheduleWindow>,
+    exclusion_windows: Vec<ScheduleWindow>,
+    min_elevation_deg: f64,
+    azimuth_mask: Vec<AzimuthMaskSector>,
+    min_duration_s: f64,
+    min_samples: u32,
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self {
+            inclusion_windows: Vec::new(),
+            exclusion_windows: Vec::new(),
+            min_elevation_deg: 0.0,
+            azimuth_mask: Vec::new(),
+            min_duration_s: 0.0,
+            min_samples: 0,
+        }
+    }
+}
+
+impl SchedulingConfig {
+    fn instant_allowed(&self, epoch_s: i64) -> bool {
+        let included = self.inclusion_windows.is_empty()
+            || self.inclusion_windows.iter().any(|w| w.contains(epoch_s));
+        let excluded = self.exclusion_windows.iter().any(|w| w.contains(epoch_s));
+        included && !excluded
+    }
+
+    fn min_elevation_for_azimuth(&self, az_deg: f64) -> f64 {
+        self.azimuth_mask.iter()
+            .find(|sector| sector.contains(az_deg))
+            .map(|sector| sector.min_elevation_deg)
+            .unwrap_or(self.min_elevation_deg)
+    }
+}
+
+/// Parses the optional `schedule_json` FFI argument into a `SchedulingConfig`,
+/// falling back to `SchedulingConfig::default()` (unconstrained) when the
+/// pointer is null or the JSON fails to parse.
+fn parse_schedule_json(schedule_json: *const c_char) -> SchedulingConfig {
+    if schedule_json.is_null() {
+        return SchedulingConfig::default();
+    }
+    let schedule_str = unsafe { CStr::from_ptr(schedule_json) }.to_string_lossy().into_owned();
+    match serde_json::from_str::<SchedulingConfig>(&schedule_str) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Schedule JSON parse error: {}", e);
+            SchedulingConfig::default()
+        }
+    }
+}
+
+/// Satellite-only topocentric altitude/azimuth at `dt`, mirroring the
+/// satellite half of `compute_topo_vectors` but without needing a target
+/// body — used to probe how long the satellite holds above the elevation
+/// mask around a candidate event.
+fn satellite_alt_az(
+    sat: &dyn SatellitePosition,
+    dt: DateTime<Utc>,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    refraction: &RefractionParams,
+) -> Result<(f64, f64), String> {
+    let sat_teme = sat.position_teme(dt)?;
+    let jd_ut1 = datetime_to_jd(ut1_from_utc(dt));
+    let gmst = gmst_rad(jd_ut1);
+    let observer_teme = mat_mul_vec(&rot_z(gmst), observer_ecef);
+    let sat_topo_teme = topocentric(&sat_teme, &observer_teme);
+    let rot_inv = rot_z(-gmst);
+    let sat_topo_ecef = mat_mul_vec(&rot_inv, &sat_topo_teme);
+    let sat_topo_sez = ecef_to_sez(&sat_topo_ecef, observer_lat_rad, observer_lon_rad);
+    let (sat_alt, sat_az) = altaz(&sat_topo_sez);
+    Ok((refraction.apparent_altitude_deg(sat_alt), sat_az))
+}
+
+/// How long, centered on `t_min`, the satellite's elevation stays at or
+/// above the schedule's mask, scanning outward from `t_min` in 1 s steps
+/// (capped at 30 min each way). Returns `(duration_s, n_samples)`; both
+/// are 0 if the satellite doesn't even clear the mask at `t_min` itself.
+#[allow(clippy::too_many_arguments)]
+fn elevation_hold_duration(
+    sat: &dyn SatellitePosition,
+    t_min: DateTime<Utc>,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    schedule: &SchedulingConfig,
+    refraction: &RefractionParams,
+) -> (f64, u32) {
+    const STEP_S: f64 = 1.0;
+    const MAX_HALF_SPAN_S: f64 = 1800.0;
+
+    let clears = |t: DateTime<Utc>| -> bool {
+        match satellite_alt_az(sat, t, observer_ecef, observer_lat_rad, observer_lon_rad, refraction) {
+            Ok((alt, az)) => alt >= schedule.min_elevation_for_azimuth(az),
+            Err(_) => false,
+        }
+    };
+
+    if !clears(t_min) {
+        return (0.0, 0);
+    }
+
+    let mut samples = 1u32;
+    let mut forward_s = 0.0;
+    let mut t = t_min;
+    while forward_s < MAX_HALF_SPAN_S {
+        let next = t + Duration::milliseconds((STEP_S * 1000.0) as i64);
+        if !clears(next) {
+            break;
+        }
+        t = next;
+        forward_s += STEP_S;
+        samples += 1;
+    }
+
+    let mut backward_s = 0.0;
+    let mut t = t_min;
+    while backward_s < MAX_HALF_SPAN_S {
+        let prev = t - Duration::milliseconds((STEP_S * 1000.0) as i64);
+        if !clears(prev) {
+            break;
+        }
+        t = prev;
+        backward_s += STEP_S;
+        samples += 1;
+    }
+
+    (forward_s + backward_s, samples)
+}
+
+// ============================================================================
+// Main Prediction Function (FFI)
+// ============================================================================
+
+/// `pressure_hpa`/`temperature_c` tune the atmospheric refraction applied to
+/// altitude gating and the reported `sat_alt_deg`/`target_alt_deg`, and
+/// default independently of each other: a negative `pressure_hpa` falls
+/// back to the standard-atmosphere pressure (1010 hPa), and a
+/// `temperature_c` at or below absolute zero falls back to the
+/// standard-atmosphere temperature (10 °C). Pass `pressure_hpa = 0.0` to
+/// disable the refraction correction.
+/// `extra_bodies_csv` is an optional (may be null) comma-separated list of
+/// additional target bodies to scan alongside the Sun and Moon, e.g.
+/// `"Venus,Jupiter"`. Unrecognized names are ignored. `schedule_json` is an
+/// optional (may be null) JSON-encoded `SchedulingConfig` restricting which
+/// events are reported by epoch window and elevation mask; a null pointer
+/// or unparseable JSON falls back to an unconstrained schedule. `columnar`
+/// selects the output shape: `false` returns the classic JSON array of
+/// per-event objects, `true` returns one `ColumnarEvents` object of parallel
+/// arrays plus a `schema_version` and `column_types` map — cheaper to parse
+/// for large result sets. `spk_kernel_data`/`spk_kernel_len` are an optional
+/// (may be null/0) JPL SPK (.bsp) kernel, e.g. DE440, for arcsecond-level
+/// Sun/Moon positions; without one, Sun/Moon use the dependency-free
+/// analytic series.
+///
+/// # Safety
+/// `spk_kernel_data` must point to at least `spk_kernel_len` readable bytes
+/// when non-null.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn predict_transits(
+    tle1: *const c_char,
+    tle2: *const c_char,
+    lat: f64,
+    lon: f64,
+    alt_m: f64,
+    start_epoch: i64,
+    end_epoch: i64,
+    max_distance_km: f64,
+    pressure_hpa: f64,
+    temperature_c: f64,
+    extra_bodies_csv: *const c_char,
+    schedule_json: *const c_char,
+    columnar: bool,
+    spk_kernel_data: *const u8,
+    spk_kernel_len: usize,
+) -> *mut c_char {
+    init_logger();
+
+    info!("ISS Transit Prediction starting");
+    info!("  Location: {:.5}°N, {:.5}°E, {}m", lat, lon, alt_m);
+
+    let tle1_str = unsafe { CStr::from_ptr(tle1) }.to_string_lossy().into_owned();
+    let tle2_str = unsafe { CStr::from_ptr(tle2) }.to_string_lossy().into_owned();
+    
+    let elements = match sgp4::Elements::from_tle(
+        Some("ISS".to_string()),
+        tle1_str.as_bytes(),
+        tle2_str.as_bytes(),
+    ) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("TLE parse error: {}", e);
+            return CString::new("[]").unwrap().into_raw();
+        }
+    };
+    
+    let start = DateTime::<Utc>::from_timestamp(start_epoch, 0).unwrap();
+    let end = DateTime::<Utc>::from_timestamp(end_epoch, 0).unwrap();
+    
+    info!("  Time: {} to {}", start, end);
+    info!("  Duration: {} days", (end - start).num_days());
+    
+    let observer_ecef = geodetic_to_ecef(lat.to_radians(), lon.to_radians(), alt_m);
+    let observer_lat_rad = lat.to_radians();
+    let observer_lon_rad = lon.to_radians();
+
+    let refraction = resolve_refraction(pressure_hpa, temperature_c);
+
+    let ephemeris_backend = resolve_ephemeris(spk_kernel_data, spk_kernel_len);
+    let ephemeris: &dyn Ephemeris = ephemeris_backend.as_ref();
+    let sat = Sgp4Satellite { elements: &elements };
+
+    let mut bodies: Vec<String> = vec!["Sun".to_string(), "Moon".to_string()];
+    if !extra_bodies_csv.is_null() {
+        let extra_str = unsafe { CStr::from_ptr(extra_bodies_csv) }.to_string_lossy().into_owned();
+        for name in extra_str.split(',') {
+            let name = name.trim();
+            if planet_elements(name).is_some() {
+                bodies.push(name.to_string());
+            } else if !name.is_empty() {
+                warn!("Ignoring unsupported target body: {}", name);
+            }
+        }
+    }
+
+    let schedule = parse_schedule_json(schedule_json);
+
+    let events = scan_transits(&sat, ephemeris, &observer_ecef, observer_lat_rad, observer_lon_rad, start, end, max_distance_km, &refraction, &bodies, &schedule, "ISS (ZARYA)");
+
+    info!("Found {} event(s)", events.len());
+
+    let json = serialize_events(events, columnar);
+    CString::new(json).unwrap().into_raw()
+}
+
+/// Direct-scanning transit search (same as main.rs): walks `[start, end]`
+/// on a `coarse_step_s` grid checking every body in `bodies` for a close
+/// approach, then refines each candidate with `refine_minimum`. No pass
+/// pre-filtering beyond a fixed 5° altitude floor. Shared by the TLE/SGP4
+/// and SP3 FFI entry points, which differ only in where `sat` gets its
+/// position from. `schedule` additionally drops events outside its
+/// inclusion/exclusion windows or that don't clear its elevation mask.
+/// `satellite_name` is copied into each `Event::satellite` field verbatim,
+/// so multi-satellite callers can scan the same observer/time grid once per
+/// satellite and still get correctly-tagged events back.
+/// Collects every matching event into a `Vec` — convenience wrapper around
+/// `scan_transits_for_each` for callers (the batch FFI functions) that need
+/// the whole-window result at once rather than as it's found.
+#[allow(clippy::too_many_arguments)]
+fn scan_transits(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    max_distance_km: f64,
+    refraction: &RefractionParams,
+    bodies: &[String],
+    schedule: &SchedulingConfig,
+    satellite_name: &str,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    scan_transits_for_each(
+        sat, ephemeris, observer_ecef, observer_lat_rad, observer_lon_rad,
+        start, end, max_distance_km, refraction, bodies, schedule, satellite_name,
+        |event| events.push(event),
+    );
+    events.sort_by_key(|e| e.time_utc.clone());
+    events
+}
+
+/// Handles a single coarse-scan close approach: runs the full
+/// `refine_minimum`/`refine_transit` refinement pipeline, applies the
+/// schedule filters, and emits an `Event` via `on_event` if it survives.
+/// Shared by `scan_transits_for_each` and `scan_transits_multi_for_each` so
+/// the two scanning loops don't duplicate this ~80-line block. Returns the
+/// refined transit time on a successful event, so the caller knows how far
+/// to skip its scan ahead.
+#[allow(clippy::too_many_arguments)]
+fn handle_close_approach(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    t: DateTime<Utc>,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    body: &str,
+    max_distance_km: f64,
+    refraction: &RefractionParams,
+    schedule: &SchedulingConfig,
+    satellite_name: &str,
+    refine_window_s: f64,
+    refine_tol_s: f64,
+    fine_step_s: f64,
+    near_margin_deg: f64,
+    on_event: &mut dyn FnMut(Event),
+) -> Option<DateTime<Utc>> {
+    match refine_minimum(sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body, refine_window_s, refine_tol_s, refraction) {
+        Ok((t_min, min_sep_deg, radius_deg, sat_alt_refined, sat_az_refined, body_alt_refined, sat_range)) => {
+            let mut kind = if min_sep_deg <= radius_deg {
+                "transit"
+            } else if min_sep_deg <= radius_deg + near_margin_deg {
+                if is_stellar_size_target(body) { "conjunction" } else { "near" }
+            } else {
+                ""
+            };
+
+            // Check if event is "reachable" (within travel distance)
+            if kind.is_empty() && sat_range > 0.0 && max_distance_km > 0.0 {
+                // Calculate ground distance needed to travel to see the transit
+                // Using small angle approximation: arc_length ≈ angle_rad × distance
+                let required_travel_km = min_sep_deg.to_radians() * sat_range;
+                if required_travel_km <= max_distance_km && body_alt_refined >= 0.0 {
+                    kind = "reachable";
+                }
+            }
+
+            if kind.is_empty() {
+                return None;
+            }
+
+            if !schedule.instant_allowed(t_min.timestamp()) {
+                return None;
+            }
+            if sat_alt_refined < schedule.min_elevation_for_azimuth(sat_az_refined) {
+                return None;
+            }
+            if schedule.min_duration_s > 0.0 || schedule.min_samples > 0 {
+                let (hold_duration_s, hold_samples) = elevation_hold_duration(
+                    sat, t_min, observer_ecef, observer_lat_rad, observer_lon_rad, schedule, refraction,
+                );
+                if hold_duration_s < schedule.min_duration_s || hold_samples < schedule.min_samples {
+                    return None;
+                }
+            }
+
+            let (speed_deg_per_s, velocity_alt_deg_per_s, velocity_az_deg_per_s, motion_direction_deg) = calculate_speed_and_duration(
+                sat, ephemeris, t_min, observer_ecef, observer_lat_rad, observer_lon_rad, body, fine_step_s, refraction
+            ).unwrap_or((0.0, 0.0, 0.0, 0.0));
+
+            let closest_refinement = refine_transit(
+                sat, ephemeris, observer_ecef, observer_lat_rad, observer_lon_rad, body,
+                t_min - Duration::seconds(30), t_min + Duration::seconds(30), refraction,
+            )
+            .ok()
+            .and_then(|refinements| {
+                refinements
+                    .into_iter()
+                    .min_by_key(|r| (r.t_closest - t_min).num_milliseconds().abs())
+            });
+
+            // Prefer refine_transit's bisected entry/exit timestamps over the
+            // constant-speed chord estimate: the satellite's apparent speed
+            // varies across the pass, so entry and exit aren't symmetric
+            // around the minimum in general.
+            let duration_s = closest_refinement
+                .as_ref()
+                .and_then(|r| match (r.entry_time, r.exit_time) {
+                    (Some(entry), Some(exit)) => {
+                        Some((exit - entry).num_milliseconds() as f64 / 1000.0)
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| calculate_transit_duration(min_sep_deg, radius_deg, speed_deg_per_s));
+
+            // How far short of the combined disk radius a near-miss/conjunction
+            // fell; `None` for an actual disk transit, which has no miss to report.
+            let miss_distance_arcmin = closest_refinement.as_ref().and_then(|r| {
+                r.miss_distance_deg.map(|deg| {
+                    info!(
+                        "Near-miss: {} closest separation {:.4}° missed the combined disk by {:.4}'",
+                        body, r.min_separation_deg, deg * 60.0
+                    );
+                    deg * 60.0
+                })
+            });
+
+            let sat_ang_size = if sat_range > 0.0 {
+                let size_km = ISS_DIMENSION_M / 1000.0;
+                (size_km / sat_range).to_degrees() * 3600.0
+            } else {
+                0.0
+            };
+
+            let ground_track = transit_ground_track(
+                sat, ephemeris, body,
+                t_min - Duration::seconds(5), t_min + Duration::seconds(5),
+                0.5,
+            );
+
+            let (moon_phase_angle_deg, moon_illuminated_fraction, moon_bright_limb_angle_deg, moon_crossing_side) =
+                if body == "Moon" {
+                    let illum = moon_illumination(datetime_to_jd(tt_from_utc(t_min)));
+                    let side = compute_topo_vectors(
+                        sat, ephemeris, t_min, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction,
+                    )
+                    .ok()
+                    .map(|(sat_topo, body_topo, _, _)| {
+                        let (ra_sat, dec_sat) = ra_dec_rad(&sat_topo);
+                        let (ra_body, dec_body) = ra_dec_rad(&body_topo);
+                        let sat_position_angle_deg = position_angle_rad(ra_body, dec_body, ra_sat, dec_sat)
+                            .to_degrees()
+                            .rem_euclid(360.0);
+                        moon_crossing_side(&illum, sat_position_angle_deg).to_string()
+                    });
+                    (Some(illum.phase_angle_deg), Some(illum.illuminated_fraction), Some(illum.bright_limb_angle_deg), side)
+                } else {
+                    (None, None, None, None)
+                };
+
+            on_event(Event {
+                time_utc: t_min.to_rfc3339(),
+                body: body.to_string(),
+                separation_arcmin: min_sep_deg * 60.0,
+                target_radius_arcmin: radius_deg * 60.0,
+                kind: kind.to_string(),
+                sat_alt_deg: sat_alt_refined,
+                sat_az_deg: sat_az_refined,
+                target_alt_deg: body_alt_refined,
+                satellite: satellite_name.to_string(),
+                speed_deg_per_s,
+                speed_arcmin_per_s: speed_deg_per_s * 60.0,
+                velocity_alt_deg_per_s,
+                velocity_az_deg_per_s,
+                motion_direction_deg,
+                duration_s,
+                sat_angular_size_arcsec: sat_ang_size,
+                sat_distance_km: sat_range,
+                ground_track,
+                miss_distance_arcmin,
+                moon_phase_angle_deg,
+                moon_illuminated_fraction,
+                moon_bright_limb_angle_deg,
+                moon_crossing_side,
+            });
+
+            info!("Event found: {} {} at {}", kind, body, t_min);
+            Some(t_min)
+        }
+        Err(e) => {
+            warn!("Refinement error: {}", e);
+            None
+        }
+    }
+}
+
+/// Same direct-scanning algorithm as `scan_transits`, but invokes `on_event`
+/// as soon as each event is found instead of accumulating a `Vec` — the
+/// shared core behind both the batch functions (`predict_transits`,
+/// `predict_transits_sp3`) and the streaming FFI entry point
+/// (`predict_transits_stream`), which differ only in what they do with each
+/// event once found. Events arrive in roughly chronological order (the scan
+/// walks `t` forward) but callers needing a strict guarantee should sort.
+#[allow(clippy::too_many_arguments)]
+fn scan_transits_for_each(
+    sat: &dyn SatellitePosition,
+    ephemeris: &dyn Ephemeris,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    max_distance_km: f64,
+    refraction: &RefractionParams,
+    bodies: &[String],
+    schedule: &SchedulingConfig,
+    satellite_name: &str,
+    mut on_event: impl FnMut(Event),
+) {
+    let coarse_step_s = 20.0;
+    let fine_step_s = 1.0;
+    let refine_window_s = 60.0;
+    let refine_tol_s = 0.001; // golden-section bracket convergence tolerance
+    let alt_min = 5.0;
+    let near_margin_deg = 0.5;
+
+    let mut t = start;
+
+    // DIRECT SCANNING ALGORITHM (same as main.rs)
+    // No pass pre-filtering - scans every 20s checking for close approaches
+    while t <= end {
+        for body in bodies.iter().map(|s| s.as_str()) {
+            match compute_topo_vectors(sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction) {
+                Ok((sat_topo, body_topo, sat_alt, body_alt)) => {
+                    if sat_alt < alt_min || body_alt < 0.0 {
+                        continue;
+                    }
+
+                    let sep = angle_between(&sat_topo, &body_topo).to_degrees();
+                    let body_distance = body_topo.norm();
+                    let body_radius_km = match body_mean_radius_km(body) {
+                        Ok(r) => r,
+                        Err(_) => continue,
+                    };
+                    let body_radius_deg = (body_radius_km / body_distance).asin().to_degrees();
+
+                    if sep <= body_radius_deg + near_margin_deg + 2.0 {
+                        if let Some(t_min) = handle_close_approach(
+                            sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body,
+                            max_distance_km, refraction, schedule, satellite_name,
+                            refine_window_s, refine_tol_s, fine_step_s, near_margin_deg,
+                            &mut on_event,
+                        ) {
+                            t = t_min + Duration::seconds(300);
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Computation error at {}: {}", t, e);
+                }
+            }
+        }
+
+        t = t + Duration::seconds(coarse_step_s as i64);
+    }
+}
+
+/// Same direct-scanning algorithm as `scan_transits_for_each`, but checks
+/// every `(satellite, satellite_name)` pair in `entries` against the same
+/// observer/body frame at each timestep instead of re-scanning the whole
+/// window once per satellite: the per-instant Sun/Moon ephemeris lookup and
+/// GMST/observer-rotation math (`compute_body_frame`) is computed once per
+/// `(t, body)` and shared across every satellite, which each only pay for
+/// their own cheap topocentric position (`sat_topo_in_frame`) unless a close
+/// approach triggers the full refinement pipeline for that one satellite.
+/// `skip_until` tracks, per satellite, the time its own scan should resume
+/// at (`start` initially, then 300s past the `t_min` that
+/// `handle_close_approach` returns for an event) so one satellite finding an
+/// event doesn't affect when the others are checked. Used by `predict_transits_multi` to amortize the shared cost
+/// across a satellite batch.
+#[allow(clippy::too_many_arguments)]
+fn scan_transits_multi_for_each(
+    entries: &[(&dyn SatellitePosition, &str)],
+    ephemeris: &dyn Ephemeris,
+    observer_ecef: &Vector3,
+    observer_lat_rad: f64,
+    observer_lon_rad: f64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    max_distance_km: f64,
+    refraction: &RefractionParams,
+    bodies: &[String],
+    schedule: &SchedulingConfig,
+    mut on_event: impl FnMut(Event),
+) {
+    let coarse_step_s = 20.0;
+    let fine_step_s = 1.0;
+    let refine_window_s = 60.0;
+    let refine_tol_s = 0.001; // golden-section bracket convergence tolerance
+    let alt_min = 5.0;
+    let near_margin_deg = 0.5;
+
+    let mut skip_until: Vec<DateTime<Utc>> = vec![start; entries.len()];
+    let mut t = start;
+
+    while t <= end {
+        for body in bodies.iter().map(|s| s.as_str()) {
+            let frame = match compute_body_frame(ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body, refraction) {
+                Ok(f) => f,
+                Err(e) => {
+                    warn!("Computation error at {}: {}", t, e);
+                    continue;
+                }
+            };
+            if frame.body_alt < 0.0 {
+                continue;
+            }
+            let body_radius_km = match body_mean_radius_km(body) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            let body_distance = frame.body_topo_teme.norm();
+            let body_radius_deg = (body_radius_km / body_distance).asin().to_degrees();
+
+            for (i, (sat, satellite_name)) in entries.iter().enumerate() {
+                if t < skip_until[i] {
+                    continue;
+                }
+
+                let (sat_topo, sat_alt) = match sat_topo_in_frame(*sat, t, &frame, observer_lat_rad, observer_lon_rad, refraction) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!("Computation error at {}: {}", t, e);
+                        continue;
+                    }
+                };
+                if sat_alt < alt_min {
+                    continue;
+                }
+
+                let sep = angle_between(&sat_topo, &frame.body_topo_teme).to_degrees();
+                if sep <= body_radius_deg + near_margin_deg + 2.0 {
+                    if let Some(t_min) = handle_close_approach(
+                        *sat, ephemeris, t, observer_ecef, observer_lat_rad, observer_lon_rad, body,
+                        max_distance_km, refraction, schedule, satellite_name,
+                        refine_window_s, refine_tol_s, fine_step_s, near_margin_deg,
+                        &mut on_event,
+                    ) {
+                        skip_until[i] = t_min + Duration::seconds(300);
+                    }
+                }
+            }
+        }
+
+        t = t + Duration::seconds(coarse_step_s as i64);
+    }
+}
+
+/// Same event schema and search parameters as `predict_transits`, but
+/// propagates the satellite from a precise IGS SP3 orbit product instead
+/// of TLE/SGP4 — sub-arcminute positioning for transit photography timing.
+/// `sp3_data` is the full text contents of an SP3 (a/b/c/d) file; query
+/// times outside its tabulated span are simply excluded from the scan.
+/// `schedule_json` is the same optional `SchedulingConfig` JSON accepted by
+/// `predict_transits`. `columnar` selects the output shape, same as
+/// `predict_transits`. `spk_kernel_data`/`spk_kernel_len` are the same
+/// optional SPK kernel accepted by `predict_transits`.
+///
+/// # Safety
+/// `spk_kernel_data` must point to at least `spk_kernel_len` readable bytes
+/// when non-null.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn predict_transits_sp3(
+    sp3_data: *const c_char,
+    lat: f64,
+    lon: f64,
+    alt_m: f64,
+    start_epoch: i64,
+    end_epoch: i64,
+    max_distance_km: f64,
+    pressure_hpa: f64,
+    temperature_c: f64,
+    extra_bodies_csv: *const c_char,
+    schedule_json: *const c_char,
+    columnar: bool,
+    spk_kernel_data: *const u8,
+    spk_kernel_len: usize,
+) -> *mut c_char {
+    init_logger();
+
+    info!("ISS Transit Prediction (SP3) starting");
+    info!("  Location: {:.5}°N, {:.5}°E, {}m", lat, lon, alt_m);
+
+    let sp3_text = unsafe { CStr::from_ptr(sp3_data) }.to_string_lossy().into_owned();
+    let sp3 = match Sp3Ephemeris::parse(&sp3_text) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("SP3 parse error: {}", e);
+            return CString::new("[]").unwrap().into_raw();
+        }
+    };
+    let sat = Sp3Satellite { ephemeris: sp3 };
+
+    let start = DateTime::<Utc>::from_timestamp(start_epoch, 0).unwrap();
+    let end = DateTime::<Utc>::from_timestamp(end_epoch, 0).unwrap();
+
+    info!("  Time: {} to {}", start, end);
+    info!("  Duration: {} days", (end - start).num_days());
+
+    let observer_ecef = geodetic_to_ecef(lat.to_radians(), lon.to_radians(), alt_m);
+    let observer_lat_rad = lat.to_radians();
+    let observer_lon_rad = lon.to_radians();
+
+    let refraction = resolve_refraction(pressure_hpa, temperature_c);
+    let ephemeris_backend = resolve_ephemeris(spk_kernel_data, spk_kernel_len);
+    let ephemeris: &dyn Ephemeris = ephemeris_backend.as_ref();
+
+    let mut bodies: Vec<String> = vec!["Sun".to_string(), "Moon".to_string()];
+    if !extra_bodies_csv.is_null() {
+        let extra_str = unsafe { CStr::from_ptr(extra_bodies_csv) }.to_string_lossy().into_owned();
+        for name in extra_str.split(',') {
+            let name = name.trim();
+            if planet_elements(name).is_some() {
+                bodies.push(name.to_string());
+            } else if !name.is_empty() {
+                warn!("Ignoring unsupported target body: {}", name);
+            }
+        }
+    }
+
+    let schedule = parse_schedule_json(schedule_json);
+
+    let events = scan_transits(&sat, ephemeris, &observer_ecef, observer_lat_rad, observer_lon_rad, start, end, max_distance_km, &refraction, &bodies, &schedule, "ISS (ZARYA)");
+
+    info!("Found {} event(s)", events.len());
+
+    let json = serialize_events(events, columnar);
+    CString::new(json).unwrap().into_raw()
+}
+
+/// Callback invoked once per event by `predict_transits_stream`: a
+/// NUL-terminated JSON object for a single `Event`, plus the opaque
+/// `user_data` pointer the caller supplied. The `*const c_char` is only
+/// valid for the duration of the call — the callback must copy anything it
+/// needs to keep, and must not call `free_json` on it.
+pub type TransitEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+/// Same search as `predict_transits`, but calls `callback` once per event as
+/// soon as it's found instead of building one JSON array covering the whole
+/// window. Avoids materializing a multi-megabyte buffer for long windows and
+/// lets the caller (e.g. Flutter) render results incrementally; peak memory
+/// is bounded regardless of window length. `user_data` is passed back to
+/// `callback` unmodified — use it to recover caller-side context (an object
+/// pointer, a channel handle, etc). Returns the number of events delivered.
+/// `spk_kernel_data`/`spk_kernel_len` are the same optional SPK kernel
+/// accepted by `predict_transits`.
+///
+/// # Safety
+/// `spk_kernel_data` must point to at least `spk_kernel_len` readable bytes
+/// when non-null.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn predict_transits_stream(
+    tle1: *const c_char,
+    tle2: *const c_char,
+    lat: f64,
+    lon: f64,
+    alt_m: f64,
+    start_epoch: i64,
+    end_epoch: i64,
+    max_distance_km: f64,
+    pressure_hpa: f64,
+    temperature_c: f64,
+    extra_bodies_csv: *const c_char,
+    schedule_json: *const c_char,
+    callback: TransitEventCallback,
+    user_data: *mut c_void,
+    spk_kernel_data: *const u8,
+    spk_kernel_len: usize,
+) -> u32 {
+    init_logger();
+
+    info!("ISS Transit Prediction (streaming) starting");
+    info!("  Location: {:.5}°N, {:.5}°E, {}m", lat, lon, alt_m);
+
+    let tle1_str = unsafe { CStr::from_ptr(tle1) }.to_string_lossy().into_owned();
+    let tle2_str = unsafe { CStr::from_ptr(tle2) }.to_string_lossy().into_owned();
+
+    let elements = match sgp4::Elements::from_tle(
+        Some("ISS".to_string()),
+        tle1_str.as_bytes(),
+        tle2_str.as_bytes(),
+    ) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("TLE parse error: {}", e);
+            return 0;
+        }
+    };
+
+    let start = DateTime::<Utc>::from_timestamp(start_epoch, 0).unwrap();
+    let end = DateTime::<Utc>::from_timestamp(end_epoch, 0).unwrap();
+
+    info!("  Time: {} to {}", start, end);
+    info!("  Duration: {} days", (end - start).num_days());
+
+    let observer_ecef = geodetic_to_ecef(lat.to_radians(), lon.to_radians(), alt_m);
+    let observer_lat_rad = lat.to_radians();
+    let observer_lon_rad = lon.to_radians();
+
+    let refraction = resolve_refraction(pressure_hpa, temperature_c);
+
+    let ephemeris_backend = resolve_ephemeris(spk_kernel_data, spk_kernel_len);
+    let ephemeris: &dyn Ephemeris = ephemeris_backend.as_ref();
+    let sat = Sgp4Satellite { elements: &elements };
+
+    let mut bodies: Vec<String> = vec!["Sun".to_string(), "Moon".to_string()];
+    if !extra_bodies_csv.is_null() {
+        let extra_str = unsafe { CStr::from_ptr(extra_bodies_csv) }.to_string_lossy().into_owned();
+        for name in extra_str.split(',') {
+            let name = name.trim();
+            if planet_elements(name).is_some() {
+                bodies.push(name.to_string());
+            } else if !name.is_empty() {
+                warn!("Ignoring unsupported target body: {}", name);
+            }
+        }
+    }
+
+    let schedule = parse_schedule_json(schedule_json);
+
+    let mut count = 0u32;
+    scan_transits_for_each(
+        &sat, ephemeris, &observer_ecef, observer_lat_rad, observer_lon_rad,
+        start, end, max_distance_km, &refraction, &bodies, &schedule, "ISS (ZARYA)",
+        |event| {
+            let json = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
+            if let Ok(c_json) = CString::new(json) {
+                callback(c_json.as_ptr(), user_data);
+                count += 1;
+            }
+        },
+    );
+
+    info!("Streamed {} event(s)", count);
+    count
+}
+
+/// One entry of the `satellites_json` array accepted by
+/// `predict_transits_multi`: a TLE pair plus the display name to tag its
+/// events with.
+#[derive(Debug, Clone, serde::Deserialize)]
+struct SatelliteTleEntry {
+    name: String,
+    tle1: String,
+    tle2: String,
+}
+
+/// Batch multi-satellite transit search: `satellites_json` is a JSON array
+/// of `{name, tle1, tle2}` records (e.g. a Starlink constellation, or the
+/// ISS plus a docking vehicle). Parses observer geodetic coordinates,
+/// builds the refraction model, and resolves `extra_bodies_csv`/
+/// `schedule_json` once up front — saving the small one-time setup cost a
+/// caller would otherwise pay again on every separate `predict_transits`
+/// call — then scans `[start_epoch, end_epoch]` once via
+/// `scan_transits_multi_for_each`, tagging each event's `satellite` field
+/// with that entry's `name`. That shared scan computes the per-instant
+/// Sun/Moon ephemeris and GMST/observer-rotation math once per timestep and
+/// reuses it across every satellite, so an N-satellite batch costs far less
+/// than N separate `predict_transits` calls. Returns all satellites' events
+/// merged into one time-sorted JSON array — the same schema
+/// `predict_transits` returns, just with `satellite` varying per entry.
+/// Entries with an unparseable TLE are skipped (logged), the rest still run.
+/// `columnar` selects the output shape, same as `predict_transits`.
+/// `spk_kernel_data`/`spk_kernel_len` are the same optional SPK kernel
+/// accepted by `predict_transits`, shared across every satellite's scan.
+///
+/// # Safety
+/// `spk_kernel_data` must point to at least `spk_kernel_len` readable bytes
+/// when non-null.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn predict_transits_multi(
+    satellites_json: *const c_char,
+    lat: f64,
+    lon: f64,
+    alt_m: f64,
+    start_epoch: i64,
+    end_epoch: i64,
+    max_distance_km: f64,
+    pressure_hpa: f64,
+    temperature_c: f64,
+    extra_bodies_csv: *const c_char,
+    schedule_json: *const c_char,
+    columnar: bool,
+    spk_kernel_data: *const u8,
+    spk_kernel_len: usize,
+) -> *mut c_char {
+    init_logger();
+
+    info!("ISS Transit Prediction (multi-satellite) starting");
+    info!("  Location: {:.5}°N, {:.5}°E, {}m", lat, lon, alt_m);
+
+    let satellites_str = unsafe { CStr::from_ptr(satellites_json) }.to_string_lossy().into_owned();
+    let satellites: Vec<SatelliteTleEntry> = match serde_json::from_str(&satellites_str) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("satellites_json parse error: {}", e);
+            return CString::new("[]").unwrap().into_raw();
+        }
+    };
+
+    let start = DateTime::<Utc>::from_timestamp(start_epoch, 0).unwrap();
+    let end = DateTime::<Utc>::from_timestamp(end_epoch, 0).unwrap();
+
+    info!("  Time: {} to {}", start, end);
+    info!("  Satellites: {}", satellites.len());
+
+    // Observer geometry and atmosphere are shared across every satellite in
+    // the set, computed once here rather than once per satellite as N
+    // separate `predict_transits` calls would.
+    let observer_ecef = geodetic_to_ecef(lat.to_radians(), lon.to_radians(), alt_m);
+    let observer_lat_rad = lat.to_radians();
+    let observer_lon_rad = lon.to_radians();
+
+    let refraction = resolve_refraction(pressure_hpa, temperature_c);
+    let ephemeris_backend = resolve_ephemeris(spk_kernel_data, spk_kernel_len);
+    let ephemeris: &dyn Ephemeris = ephemeris_backend.as_ref();
+
+    let mut bodies: Vec<String> = vec!["Sun".to_string(), "Moon".to_string()];
+    if !extra_bodies_csv.is_null() {
+        let extra_str = unsafe { CStr::from_ptr(extra_bodies_csv) }.to_string_lossy().into_owned();
+        for name in extra_str.split(',') {
+            let name = name.trim();
+            if planet_elements(name).is_some() {
+                bodies.push(name.to_string());
+            } else if !name.is_empty() {
+                warn!("Ignoring unsupported target body: {}", name);
             }
         }
-        
-        t = t + Duration::seconds(coarse_step_s as i64);
     }
-    
+
+    let schedule = parse_schedule_json(schedule_json);
+
+    // Parse every TLE up front so its `sgp4::Elements` outlives the shared
+    // scan below (`Sgp4Satellite` just borrows it) and the invalid ones can
+    // be skipped before the scan starts.
+    let mut parsed_satellites: Vec<(sgp4::Elements, &str)> = Vec::new();
+    for entry in &satellites {
+        match sgp4::Elements::from_tle(
+            Some(entry.name.clone()),
+            entry.tle1.as_bytes(),
+            entry.tle2.as_bytes(),
+        ) {
+            Ok(elements) => parsed_satellites.push((elements, entry.name.as_str())),
+            Err(e) => warn!("TLE parse error for {}: {}", entry.name, e),
+        }
+    }
+    let sats: Vec<Sgp4Satellite> = parsed_satellites
+        .iter()
+        .map(|(elements, _)| Sgp4Satellite { elements })
+        .collect();
+    let entries: Vec<(&dyn SatellitePosition, &str)> = sats
+        .iter()
+        .zip(parsed_satellites.iter())
+        .map(|(sat, (_, name))| (sat as &dyn SatellitePosition, *name))
+        .collect();
+
+    let mut events = Vec::new();
+    scan_transits_multi_for_each(
+        &entries, ephemeris, &observer_ecef, observer_lat_rad, observer_lon_rad,
+        start, end, max_distance_km, &refraction, &bodies, &schedule,
+        |event| events.push(event),
+    );
+
     events.sort_by_key(|e| e.time_utc.clone());
-    
-    info!("Found {} event(s)", events.len());
-    
-    let json = serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string());
+
+    info!("Found {} event(s) across {} satellite(s)", events.len(), satellites.len());
+
+    let json = serialize_events(events, columnar);
     CString::new(json).unwrap().into_raw()
 }
 
@@ -679,6 +3419,45 @@ mod tests {
         assert!((jd - 2451545.0).abs() < 0.1, "J2000 JD should be ~2451545.0");
     }
 
+    #[test]
+    fn test_tai_minus_utc_s_tracks_leap_second_table() {
+        // Just before the 2017-01-01 leap second, and just after.
+        let before = Utc.with_ymd_and_hms(2016, 12, 31, 23, 59, 59).unwrap();
+        let after = Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 1).unwrap();
+
+        assert_eq!(tai_minus_utc_s(before), 36.0);
+        assert_eq!(tai_minus_utc_s(after), 37.0);
+
+        // Dates far beyond the table hold at the last known offset.
+        assert_eq!(tai_minus_utc_s(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap()), 37.0);
+    }
+
+    #[test]
+    fn test_tt_from_utc_is_about_69_seconds_ahead() {
+        let dt = Utc.with_ymd_and_hms(2025, 10, 5, 0, 0, 0).unwrap();
+        let tt = tt_from_utc(dt);
+
+        // TT - UTC = (TAI - UTC) + 32.184s = 37 + 32.184 = 69.184s today.
+        let offset_s = (tt - dt).num_milliseconds() as f64 / 1000.0;
+        assert!((offset_s - 69.184).abs() < 0.001, "TT offset should be ~69.184s, got {offset_s}");
+    }
+
+    #[test]
+    fn test_ut1_from_utc_differs_from_tt_by_delta_t() {
+        let dt = Utc.with_ymd_and_hms(2025, 10, 5, 0, 0, 0).unwrap();
+        let tt = tt_from_utc(dt);
+        let ut1 = ut1_from_utc(dt);
+
+        let delta_t_observed = (tt - ut1).num_milliseconds() as f64 / 1000.0;
+        assert!((delta_t_observed - delta_t_s(dt)).abs() < 0.001,
+                "TT - UT1 should equal the Espenak-Meeus Delta-T estimate");
+
+        // UT1 should be within a couple of minutes of UTC; a wiring bug
+        // (e.g. swapping signs) would throw this off by hours.
+        let ut1_minus_utc = (ut1 - dt).num_milliseconds() as f64 / 1000.0;
+        assert!(ut1_minus_utc.abs() < 120.0, "UT1 should track UTC closely, got {ut1_minus_utc}s offset");
+    }
+
     #[test]
     fn test_gmst() {
         // Test GMST calculation for a known time
@@ -731,6 +3510,134 @@ mod tests {
                 "Moon distance should be ~384,400 km ± range");
     }
 
+    #[test]
+    fn test_moon_illumination_near_full_and_new_moon() {
+        // 2025-10-07: close to the October 2025 full moon.
+        let full = Utc.with_ymd_and_hms(2025, 10, 7, 0, 0, 0).unwrap();
+        let full_illum = moon_illumination(datetime_to_jd(full));
+        assert!(full_illum.illuminated_fraction > 0.9,
+                "near full moon should be mostly illuminated, got {}", full_illum.illuminated_fraction);
+        assert!(full_illum.phase_angle_deg < 30.0,
+                "near full moon the phase angle should be small, got {}", full_illum.phase_angle_deg);
+
+        // 2025-10-21: close to the October 2025 new moon.
+        let new = Utc.with_ymd_and_hms(2025, 10, 21, 12, 0, 0).unwrap();
+        let new_illum = moon_illumination(datetime_to_jd(new));
+        assert!(new_illum.illuminated_fraction < 0.1,
+                "near new moon should be mostly dark, got {}", new_illum.illuminated_fraction);
+        assert!(new_illum.phase_angle_deg > 150.0,
+                "near new moon the phase angle should be near 180°, got {}", new_illum.phase_angle_deg);
+
+        assert!(full_illum.bright_limb_angle_deg >= 0.0 && full_illum.bright_limb_angle_deg < 360.0);
+    }
+
+    #[test]
+    fn test_analytic_ephemeris_matches_raw_series() {
+        let dt = Utc.with_ymd_and_hms(2025, 10, 5, 12, 0, 0).unwrap();
+        let jd = datetime_to_jd(dt);
+        let ephemeris = AnalyticEphemeris;
+
+        assert_eq!(ephemeris.sun_eci(jd).norm(), sun_position_eci(jd).norm());
+        assert_eq!(ephemeris.moon_eci(jd).norm(), moon_position_eci(jd).norm());
+    }
+
+    #[test]
+    fn test_chebyshev_eval_matches_known_polynomials() {
+        // T0(x) = 1
+        assert!((chebyshev_eval(&[1.0], 0.37) - 1.0).abs() < 1e-12);
+        // c0 + c1*T1(x) = c0 + c1*x
+        assert!((chebyshev_eval(&[2.0, 3.0], 0.5) - 3.5).abs() < 1e-12);
+        // c2*T2(x) = c2*(2x^2 - 1), with c0=c1=0
+        let tau = 0.4_f64;
+        let expected = 2.0 * (2.0 * tau * tau - 1.0);
+        assert!((chebyshev_eval(&[0.0, 0.0, 2.0], tau) - expected).abs() < 1e-12);
+    }
+
+    /// Hand-builds a minimal single-segment DAF/SPK byte buffer (one Type 2
+    /// Chebyshev record covering `target` relative to `center`), used by
+    /// the test below to check that `SpkEphemeris` parses the summary and
+    /// evaluates the record correctly. This stands in for a real DE-series
+    /// kernel, which isn't available in this environment.
+    fn build_synthetic_spk(target: i32, center: i32, coeffs_per_component: usize) -> Vec<u8> {
+        const ND: i32 = 2;
+        const NI: i32 = 6;
+        let mut bytes = vec![0u8; SPK_RECORD_LEN * 3];
+
+        bytes[0..8].copy_from_slice(b"DAF/SPK ");
+        bytes[8..12].copy_from_slice(&ND.to_le_bytes());
+        bytes[12..16].copy_from_slice(&NI.to_le_bytes());
+        bytes[76..80].copy_from_slice(&2i32.to_le_bytes()); // FWARD: summary record 2
+
+        // Summary record (record 2, byte offset 1024).
+        let rec2 = SPK_RECORD_LEN;
+        bytes[rec2..rec2 + 8].copy_from_slice(&0f64.to_le_bytes()); // NEXT: none
+        bytes[rec2 + 16..rec2 + 24].copy_from_slice(&1f64.to_le_bytes()); // NSUM: 1 summary
+
+        // Addresses are 1-based 8-byte-word offsets from the start of the
+        // whole file, per the DAF spec — not relative to this record.
+        let rec3 = SPK_RECORD_LEN * 2;
+        let data_addr_start = rec3 / 8 + 1;
+        let rsize = 2 + coeffs_per_component * 3; // MID, RADIUS, then 3 components of coeffs
+        let n_records = 1_usize;
+        let dir_len = 4; // INIT, INTLEN, RSIZE, N
+        let data_addr_end = data_addr_start + n_records * rsize + dir_len - 1;
+
+        let summary = rec2 + 24;
+        bytes[summary..summary + 8].copy_from_slice(&(-1000.0f64).to_le_bytes()); // start_et
+        bytes[summary + 8..summary + 16].copy_from_slice(&(1000.0f64).to_le_bytes()); // end_et
+        let int_base = summary + (ND as usize) * 8;
+        bytes[int_base..int_base + 4].copy_from_slice(&target.to_le_bytes());
+        bytes[int_base + 4..int_base + 8].copy_from_slice(&center.to_le_bytes());
+        bytes[int_base + 12..int_base + 16].copy_from_slice(&2i32.to_le_bytes()); // data_type 2
+        bytes[int_base + 16..int_base + 20].copy_from_slice(&(data_addr_start as i32).to_le_bytes());
+        bytes[int_base + 20..int_base + 24].copy_from_slice(&(data_addr_end as i32).to_le_bytes());
+
+        // Data record (record 3, byte offset 2048): MID=0, RADIUS=1000,
+        // then coeffs for x, y, z, followed by the directory.
+        let data_off = (data_addr_start - 1) * 8;
+        bytes[data_off..data_off + 8].copy_from_slice(&0f64.to_le_bytes()); // MID
+        bytes[data_off + 8..data_off + 16].copy_from_slice(&1000.0f64.to_le_bytes()); // RADIUS
+        let coeff_base = data_off + 16;
+        let comp_coeffs: [[f64; 2]; 3] = [[100.0, 1.0], [200.0, 2.0], [300.0, 3.0]];
+        for (comp, coeffs) in comp_coeffs.iter().enumerate() {
+            for (k, c) in coeffs.iter().enumerate() {
+                let off = coeff_base + (comp * coeffs_per_component + k) * 8;
+                bytes[off..off + 8].copy_from_slice(&(*c).to_le_bytes());
+            }
+        }
+        let dir_off = (data_addr_end - dir_len) * 8;
+        bytes[dir_off..dir_off + 8].copy_from_slice(&(-1000.0f64).to_le_bytes()); // INIT
+        bytes[dir_off + 8..dir_off + 16].copy_from_slice(&(2000.0f64).to_le_bytes()); // INTLEN
+        bytes[dir_off + 16..dir_off + 24].copy_from_slice(&(rsize as f64).to_le_bytes()); // RSIZE
+        bytes[dir_off + 24..dir_off + 32].copy_from_slice(&(n_records as f64).to_le_bytes()); // N
+
+        bytes
+    }
+
+    #[test]
+    fn test_spk_ephemeris_parses_synthetic_kernel() {
+        let bytes = build_synthetic_spk(NAIF_BODY_SUN, NAIF_CENTER_SSB, 2);
+        let spk = SpkEphemeris::load(bytes).expect("synthetic kernel should parse");
+
+        let segment = spk.find_segment(NAIF_BODY_SUN, NAIF_CENTER_SSB, 0.0)
+            .expect("segment should cover et=0");
+        let pos = spk.eval_segment(segment, 0.0).expect("segment should evaluate");
+
+        // At tau=0 (et == MID), T1(0)=0 so only the constant terms survive.
+        assert!((pos.x - 100.0).abs() < 1e-9);
+        assert!((pos.y - 200.0).abs() < 1e-9);
+        assert!((pos.z - 300.0).abs() < 1e-9);
+
+        assert!(spk.find_segment(NAIF_BODY_SUN, NAIF_CENTER_SSB, 5000.0).is_none(),
+                "et outside the segment's time span should not match");
+    }
+
+    #[test]
+    fn test_spk_ephemeris_rejects_non_spk_bytes() {
+        assert!(SpkEphemeris::load(vec![0u8; 4096]).is_err());
+        assert!(SpkEphemeris::load(vec![0u8; 10]).is_err());
+    }
+
     #[test]
     fn test_altaz_conversion() {
         // Test altitude/azimuth calculation
@@ -826,6 +3733,466 @@ mod tests {
         assert!(duration_offset < duration, "Off-center transit should be shorter");
     }
 
+    #[test]
+    fn test_refraction_lifts_horizon_altitude() {
+        let refraction = RefractionParams::default();
+
+        // At the horizon, Bennett's formula gives ~34 arcmin (~0.57°) of lift.
+        let apparent = refraction.apparent_altitude_deg(0.0);
+        assert!((apparent - 0.57).abs() < 0.05, "horizon refraction should be ~34 arcmin, got {apparent}");
+
+        // High in the sky, refraction should be negligible.
+        let apparent_high = refraction.apparent_altitude_deg(80.0);
+        assert!((apparent_high - 80.0).abs() < 0.01, "refraction should vanish near zenith");
+
+        // Well below the horizon, the correction is not applied.
+        assert_eq!(refraction.apparent_altitude_deg(-10.0), -10.0);
+    }
+
+    #[test]
+    fn test_planet_position_distance_is_plausible() {
+        let dt = Utc.with_ymd_and_hms(2025, 10, 5, 12, 0, 0).unwrap();
+        let jd = datetime_to_jd(dt);
+
+        for (planet, min_au, max_au) in [
+            ("Venus", 0.2, 1.8),
+            ("Mars", 0.3, 2.7),
+            ("Jupiter", 3.8, 6.5),
+            ("Saturn", 8.0, 11.0),
+        ] {
+            let pos = planet_position_eci(jd, planet).expect("planet should resolve");
+            let distance_au = pos.norm() / AU_KM;
+            assert!(
+                distance_au > min_au && distance_au < max_au,
+                "{planet} geocentric distance should be plausible, got {distance_au} AU"
+            );
+        }
+    }
+
+    #[test]
+    fn test_body_dispatch_helpers_cover_all_supported_bodies() {
+        let jd = datetime_to_jd(Utc.with_ymd_and_hms(2025, 10, 5, 0, 0, 0).unwrap());
+        for body in ["Sun", "Moon", "Venus", "Mars", "Jupiter", "Saturn"] {
+            assert!(body_position_eci(jd, body).is_ok(), "{body} should have a position");
+            assert!(body_mean_radius_km(body).is_ok(), "{body} should have a mean radius");
+        }
+        assert!(body_position_eci(jd, "Pluto").is_err());
+        assert!(body_mean_radius_km("Pluto").is_err());
+    }
+
+    #[test]
+    fn test_is_stellar_size_target_distinguishes_planets_from_sun_and_moon() {
+        for body in ["Venus", "Mars", "Jupiter", "Saturn"] {
+            assert!(is_stellar_size_target(body), "{body} should be a stellar-size target");
+        }
+        for body in ["Sun", "Moon"] {
+            assert!(!is_stellar_size_target(body), "{body} should not be a stellar-size target");
+        }
+    }
+
+    #[test]
+    fn test_rot_x_rot_y_preserve_norm() {
+        let v = Vector3::new(3.0, -4.0, 5.0);
+        let rx = mat_mul_vec(&rot_x(0.7), &v);
+        let ry = mat_mul_vec(&rot_y(1.3), &v);
+
+        assert!((rx.norm() - v.norm()).abs() < 1e-9, "rot_x should preserve vector norm");
+        assert!((ry.norm() - v.norm()).abs() < 1e-9, "rot_y should preserve vector norm");
+    }
+
+    #[test]
+    fn test_precession_matrix_is_near_identity_at_j2000() {
+        let p = precession_matrix(0.0);
+        let v = Vector3::new(1.0, 2.0, 3.0);
+        let rotated = mat_mul_vec(&p, &v);
+
+        assert!((rotated.x - v.x).abs() < 1e-9, "precession at T=0 should be the identity");
+        assert!((rotated.y - v.y).abs() < 1e-9);
+        assert!((rotated.z - v.z).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nutation_angles_are_arcsecond_scale() {
+        let (dpsi_rad, deps_rad, eps_rad) = nutation_angles(0.25); // a few years past J2000
+
+        // Nutation terms are a handful of arcseconds; much smaller than a degree.
+        assert!(dpsi_rad.abs() < 30.0 * ARCSEC_TO_RAD, "delta-psi should be arcsecond scale");
+        assert!(deps_rad.abs() < 30.0 * ARCSEC_TO_RAD, "delta-eps should be arcsecond scale");
+        assert!((eps_rad.to_degrees() - 23.4).abs() < 0.1, "mean obliquity should be ~23.4°");
+    }
+
+    #[test]
+    fn test_golden_section_minimize_stays_in_window() {
+        let tle1 = "1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990";
+        let tle2 = "2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279";
+        let elements = sgp4::Elements::from_tle(
+            Some("ISS (ZARYA)".to_string()),
+            tle1.as_bytes(),
+            tle2.as_bytes(),
+        ).expect("Failed to parse TLE");
+
+        let t_center = DateTime::<Utc>::from_naive_utc_and_offset(elements.datetime, Utc);
+        let observer_ecef = geodetic_to_ecef(48.8566_f64.to_radians(), 2.3522_f64.to_radians(), 35.0);
+
+        let sat = Sgp4Satellite { elements: &elements };
+        let offset = golden_section_minimize(
+            &sat,
+            &AnalyticEphemeris,
+            t_center,
+            60.0,
+            0.001,
+            &observer_ecef,
+            48.8566_f64.to_radians(),
+            2.3522_f64.to_radians(),
+            "Sun",
+            &RefractionParams::default(),
+        ).expect("golden-section search should succeed");
+
+        assert!(offset >= -60.0 && offset <= 60.0, "minimizer should stay within the refine window");
+    }
+
+    #[test]
+    fn test_refine_transit_finds_entry_and_exit() {
+        let tle1 = "1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990";
+        let tle2 = "2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279";
+        let elements = sgp4::Elements::from_tle(
+            Some("ISS (ZARYA)".to_string()),
+            tle1.as_bytes(),
+            tle2.as_bytes(),
+        ).expect("Failed to parse TLE");
+
+        let t_center = DateTime::<Utc>::from_naive_utc_and_offset(elements.datetime, Utc);
+        let observer_ecef = geodetic_to_ecef(48.8566_f64.to_radians(), 2.3522_f64.to_radians(), 35.0);
+
+        let sat = Sgp4Satellite { elements: &elements };
+        let refinements = refine_transit(
+            &sat,
+            &AnalyticEphemeris,
+            &observer_ecef,
+            48.8566_f64.to_radians(),
+            2.3522_f64.to_radians(),
+            "Sun",
+            t_center - Duration::seconds(30),
+            t_center + Duration::seconds(30),
+            &RefractionParams::default(),
+        ).expect("refine_transit should succeed");
+
+        for r in &refinements {
+            assert!(r.min_separation_deg >= 0.0);
+            match (r.entry_time, r.exit_time, r.miss_distance_deg) {
+                (Some(entry), Some(exit), None) => {
+                    assert!(entry <= r.t_closest && r.t_closest <= exit,
+                            "entry/exit should bracket the closest-approach instant");
+                }
+                (None, None, Some(miss)) => {
+                    assert!(miss > 0.0, "a near-miss should report a positive miss distance");
+                }
+                other => panic!("expected either entry/exit or a miss distance, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_bisect_separation_crossing_clamps_when_no_sign_change() {
+        let tle1 = "1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990";
+        let tle2 = "2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279";
+        let elements = sgp4::Elements::from_tle(
+            Some("ISS (ZARYA)".to_string()),
+            tle1.as_bytes(),
+            tle2.as_bytes(),
+        ).expect("Failed to parse TLE");
+
+        let t_center = DateTime::<Utc>::from_naive_utc_and_offset(elements.datetime, Utc);
+        let observer_ecef = geodetic_to_ecef(48.8566_f64.to_radians(), 2.3522_f64.to_radians(), 35.0);
+
+        // A target separation no real pass will ever cross: both endpoints
+        // land on the same side, so the bracket should clamp to an endpoint
+        // rather than loop forever.
+        let sat = Sgp4Satellite { elements: &elements };
+        let t = bisect_separation_crossing(
+            &sat,
+            &AnalyticEphemeris,
+            t_center,
+            t_center + Duration::seconds(1),
+            -1.0,
+            &observer_ecef,
+            48.8566_f64.to_radians(),
+            2.3522_f64.to_radians(),
+            "Sun",
+            &RefractionParams::default(),
+        ).expect("bisection should not error");
+
+        assert!(t == t_center || t == t_center + Duration::seconds(1));
+    }
+
+    #[test]
+    fn test_ecef_to_geodetic_roundtrip() {
+        let lat_deg: f64 = 48.8566;
+        let lon_deg: f64 = 2.3522;
+        let ecef = geodetic_to_ecef(lat_deg.to_radians(), lon_deg.to_radians(), 0.0);
+
+        let (lat_out, lon_out) = ecef_to_geodetic(&ecef);
+
+        assert!((lat_out - lat_deg).abs() < 1e-6, "latitude should round-trip");
+        assert!((lon_out - lon_deg).abs() < 1e-6, "longitude should round-trip");
+    }
+
+    #[test]
+    fn test_line_ellipsoid_intersection_near_root() {
+        // A point straight above the equator/prime-meridian, looking "down"
+        // (toward the origin) should hit the surface near (0, 0).
+        let body = Vector3::new(1_000_000.0, 0.0, 0.0);
+        let sat = Vector3::new(7000.0, 0.0, 0.0);
+
+        let hit = line_ellipsoid_intersection(&body, &sat).expect("ray should hit the ellipsoid");
+
+        assert!((hit.norm() - EARTH_RADIUS_KM).abs() < 1.0, "hit point should lie on the ellipsoid");
+        assert!(hit.x > 0.0, "hit point should be on the near side, beyond the satellite");
+    }
+
+    #[test]
+    fn test_transit_ground_track_produces_points_on_ellipsoid() {
+        let tle1 = "1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990";
+        let tle2 = "2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279";
+        let elements = sgp4::Elements::from_tle(
+            Some("ISS (ZARYA)".to_string()),
+            tle1.as_bytes(),
+            tle2.as_bytes(),
+        ).expect("Failed to parse TLE");
+
+        let t0 = DateTime::<Utc>::from_naive_utc_and_offset(elements.datetime, Utc);
+        // A full orbit (~93 min) guarantees the ground track crosses the
+        // sunlit hemisphere at least once, regardless of where in its
+        // orbit the satellite starts relative to the Sun.
+        let t1 = t0 + Duration::seconds(93 * 60);
+
+        let sat = Sgp4Satellite { elements: &elements };
+        let track = transit_ground_track(&sat, &AnalyticEphemeris, "Sun", t0, t1, 30.0);
+
+        assert!(!track.is_empty(), "should produce at least one centerline point over a full orbit");
+        for point in &track {
+            assert!(point.lat_deg >= -90.0 && point.lat_deg <= 90.0);
+            assert!(point.lon_deg >= -180.0 && point.lon_deg <= 180.0);
+            assert!(point.half_width_km >= 0.0, "corridor half-width should never be negative");
+        }
+    }
+
+    #[test]
+    fn test_circle_overlap_fraction_pure_geometry() {
+        // Disks far enough apart never touch.
+        assert_eq!(circle_overlap_fraction(1.0, 0.5, 10.0), 0.0);
+
+        // Equal-radius disks exactly coincident fully overlap.
+        assert!((circle_overlap_fraction(1.0, 1.0, 0.0) - 1.0).abs() < 1e-9);
+
+        // A smaller disk fully inside a larger one (annular case) covers
+        // exactly its own area fraction of the larger disk.
+        let frac = circle_overlap_fraction(2.0, 0.5, 0.0);
+        assert!((frac - 0.25 * 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_eclipse_local_circumstances_detects_known_eclipse() {
+        // 2024-04-08 total solar eclipse, observed near Dallas, TX.
+        let t_start = Utc.with_ymd_and_hms(2024, 4, 8, 16, 0, 0).unwrap();
+        let t_end = Utc.with_ymd_and_hms(2024, 4, 8, 20, 0, 0).unwrap();
+        let observer_ecef = geodetic_to_ecef(32.7767_f64.to_radians(), (-96.7970_f64).to_radians(), 130.0);
+
+        let circumstances = eclipse_local_circumstances(&observer_ecef, t_start, t_end, &AnalyticEphemeris)
+            .expect("eclipse search should not error")
+            .expect("a solar eclipse should be found on this known eclipse date");
+
+        assert!(circumstances.magnitude > 0.5, "should be a deep eclipse, got magnitude {}", circumstances.magnitude);
+        assert!(circumstances.obscuration > 0.0 && circumstances.obscuration <= 1.0);
+        assert!(["partial", "total", "annular"].contains(&circumstances.kind.as_str()));
+
+        let first = DateTime::parse_from_rfc3339(circumstances.first_contact_utc.as_ref().unwrap()).unwrap();
+        let max = DateTime::parse_from_rfc3339(&circumstances.max_eclipse_utc).unwrap();
+        let last = DateTime::parse_from_rfc3339(circumstances.last_contact_utc.as_ref().unwrap()).unwrap();
+        assert!(first <= max && max <= last, "contacts should bracket the time of greatest eclipse");
+    }
+
+    #[test]
+    fn test_eclipse_local_circumstances_none_at_full_moon() {
+        // 2025-10-07: close to the October 2025 full moon — Sun and Moon
+        // are on opposite sides of the sky, nowhere near occultation.
+        let t_start = Utc.with_ymd_and_hms(2025, 10, 7, 0, 0, 0).unwrap();
+        let t_end = Utc.with_ymd_and_hms(2025, 10, 7, 1, 0, 0).unwrap();
+        let observer_ecef = geodetic_to_ecef(48.8566_f64.to_radians(), 2.3522_f64.to_radians(), 35.0);
+
+        let circumstances = eclipse_local_circumstances(&observer_ecef, t_start, t_end, &AnalyticEphemeris)
+            .expect("eclipse search should not error");
+        assert!(circumstances.is_none(), "no eclipse should be found at full moon");
+    }
+
+    #[test]
+    fn test_hermite_interpolate_matches_cubic_exactly() {
+        // A cubic is exactly representable by a degree-3 Hermite interpolant,
+        // so adding more (value, derivative) nodes from the same cubic
+        // should still reproduce it everywhere, not just at the nodes.
+        let f = |x: f64| x.powi(3) - 2.0 * x * x + x + 5.0;
+        let df = |x: f64| 3.0 * x * x - 4.0 * x + 1.0;
+
+        let xs = vec![0.0, 1.0, 2.0, 3.0];
+        let ys: Vec<f64> = xs.iter().map(|&x| f(x)).collect();
+        let dys: Vec<f64> = xs.iter().map(|&x| df(x)).collect();
+
+        for &x in &[0.5, 1.5, 2.7] {
+            let got = hermite_interpolate(&xs, &ys, &dys, x);
+            assert!((got - f(x)).abs() < 1e-9, "Hermite should reconstruct the source cubic at x={x}");
+        }
+    }
+
+    #[test]
+    fn test_lagrange_interpolate_matches_quadratic_exactly() {
+        let f = |x: f64| x * x - 3.0 * x + 2.0;
+        let xs = vec![0.0, 1.0, 2.0, 3.0];
+        let ys: Vec<f64> = xs.iter().map(|&x| f(x)).collect();
+
+        let got = lagrange_interpolate(&xs, &ys, 2.5);
+        assert!((got - f(2.5)).abs() < 1e-9, "Lagrange should reconstruct the source quadratic");
+    }
+
+    /// Builds a minimal SP3 file for vehicle "ISS" tracing out linear
+    /// motion (constant velocity) across `n_epochs` samples `step_s` apart,
+    /// starting at 2024-01-01T00:00:00Z. Linear motion makes both Hermite
+    /// and Lagrange interpolation exact, so tests can check against the
+    /// closed-form position directly.
+    fn build_linear_sp3(n_epochs: i64, step_s: i64, with_velocity: bool) -> String {
+        let pos0 = (1000.0, 2000.0, 3000.0);
+        let vel = (1.0, -2.0, 0.5); // km/s
+        let mut text = String::from("#cP2024  1  1  0  0  0.00000000\n+    1   ISS\n%c G  cc GPS ccc cccc cccc cccc cccc ccccc ccccc ccccc ccccc\n");
+        for i in 0..n_epochs {
+            let t_s = (i * step_s) as f64;
+            text.push_str(&format!(
+                "*  2024  1  1  {:02}  {:02}  {:09.6}\n",
+                (t_s as i64 / 3600) % 24,
+                (t_s as i64 / 60) % 60,
+                t_s % 60.0
+            ));
+            let (x, y, z) = (pos0.0 + vel.0 * t_s, pos0.1 + vel.1 * t_s, pos0.2 + vel.2 * t_s);
+            text.push_str(&format!("PISS  {:14.6}{:14.6}{:14.6}{:14.6}\n", x, y, z, 0.0));
+            if with_velocity {
+                // SP3 velocity units are dm/s: km/s * 1e4.
+                text.push_str(&format!("VISS  {:14.6}{:14.6}{:14.6}{:14.6}\n", vel.0 * 1e4, vel.1 * 1e4, vel.2 * 1e4, 0.0));
+            }
+        }
+        text.push_str("EOF\n");
+        text
+    }
+
+    #[test]
+    fn test_sp3_ephemeris_interpolates_linear_motion_with_hermite() {
+        let text = build_linear_sp3(10, 60, true);
+        let sp3 = Sp3Ephemeris::parse(&text).expect("should parse a well-formed SP3 file");
+
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let query = t0 + Duration::seconds(150); // between tabulated epochs
+        let pos = sp3.interpolate_ecef(query).expect("query time is within the tabulated span");
+
+        assert!((pos.x - (1000.0 + 1.0 * 150.0)).abs() < 1e-6, "x should match the linear track");
+        assert!((pos.y - (2000.0 - 2.0 * 150.0)).abs() < 1e-6, "y should match the linear track");
+        assert!((pos.z - (3000.0 + 0.5 * 150.0)).abs() < 1e-6, "z should match the linear track");
+    }
+
+    #[test]
+    fn test_sp3_ephemeris_falls_back_to_lagrange_without_velocity() {
+        let text = build_linear_sp3(12, 60, false);
+        let sp3 = Sp3Ephemeris::parse(&text).expect("should parse a well-formed SP3 file");
+
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let query = t0 + Duration::seconds(330);
+        let pos = sp3.interpolate_ecef(query).expect("query time is within the tabulated span");
+
+        assert!((pos.x - (1000.0 + 1.0 * 330.0)).abs() < 1e-6, "x should match the linear track");
+    }
+
+    #[test]
+    fn test_sp3_ephemeris_rejects_query_outside_tabulated_span() {
+        let text = build_linear_sp3(5, 60, true);
+        let sp3 = Sp3Ephemeris::parse(&text).expect("should parse a well-formed SP3 file");
+
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let result = sp3.interpolate_ecef(t0 - Duration::seconds(10));
+        assert!(result.is_err(), "query time before the first epoch should be rejected");
+    }
+
+    #[test]
+    fn test_sp3_ephemeris_skips_sentinel_and_other_vehicle_records() {
+        let mut text = build_linear_sp3(5, 60, true);
+        // Insert a bad (all-zero sentinel) record for "ISS" and a record
+        // for a different vehicle, both of which should be ignored.
+        text.push_str("*  2024  1  1  0  5  0.00000000\n");
+        text.push_str("PISS        0.000000      0.000000      0.000000 999999.999999\n");
+        text.push_str("PGPS    11111.111111  22222.222222  33333.333333      0.000000\n");
+
+        let sp3 = Sp3Ephemeris::parse(&text).expect("should parse despite the bad/foreign records");
+        assert!(!sp3.epochs.values().any(|e| e.position_km.x == 0.0 && e.position_km.y == 0.0 && e.position_km.z == 0.0),
+                "sentinel position should have been skipped");
+        assert!(!sp3.epochs.values().any(|e| e.position_km.x == 11111.111111),
+                "records for a different vehicle should have been ignored");
+    }
+
+    #[test]
+    fn test_schedule_window_contains_is_inclusive_both_ends() {
+        let w = ScheduleWindow { start_epoch: 100, end_epoch: 200 };
+        assert!(w.contains(100));
+        assert!(w.contains(200));
+        assert!(w.contains(150));
+        assert!(!w.contains(99));
+        assert!(!w.contains(201));
+    }
+
+    #[test]
+    fn test_azimuth_mask_sector_contains_is_half_open() {
+        let s = AzimuthMaskSector { az_start_deg: 45.0, az_end_deg: 135.0, min_elevation_deg: 20.0 };
+        assert!(s.contains(45.0));
+        assert!(s.contains(100.0));
+        assert!(!s.contains(135.0));
+        assert!(!s.contains(0.0));
+    }
+
+    #[test]
+    fn test_scheduling_config_default_allows_any_instant() {
+        let schedule = SchedulingConfig::default();
+        assert!(schedule.instant_allowed(0));
+        assert!(schedule.instant_allowed(i64::MAX));
+        assert_eq!(schedule.min_elevation_for_azimuth(123.0), 0.0);
+    }
+
+    #[test]
+    fn test_scheduling_config_inclusion_and_exclusion_windows() {
+        let schedule = SchedulingConfig {
+            inclusion_windows: vec![ScheduleWindow { start_epoch: 0, end_epoch: 1000 }],
+            exclusion_windows: vec![ScheduleWindow { start_epoch: 400, end_epoch: 600 }],
+            ..SchedulingConfig::default()
+        };
+
+        assert!(schedule.instant_allowed(100), "inside inclusion, outside exclusion");
+        assert!(!schedule.instant_allowed(500), "inside both inclusion and exclusion");
+        assert!(!schedule.instant_allowed(1500), "outside the only inclusion window");
+    }
+
+    #[test]
+    fn test_scheduling_config_min_elevation_uses_azimuth_mask_sector() {
+        let schedule = SchedulingConfig {
+            min_elevation_deg: 10.0,
+            azimuth_mask: vec![AzimuthMaskSector { az_start_deg: 180.0, az_end_deg: 270.0, min_elevation_deg: 40.0 }],
+            ..SchedulingConfig::default()
+        };
+
+        assert_eq!(schedule.min_elevation_for_azimuth(200.0), 40.0, "should use the sector's override");
+        assert_eq!(schedule.min_elevation_for_azimuth(90.0), 10.0, "should fall back to the global mask");
+    }
+
+    #[test]
+    fn test_scheduling_config_deserializes_with_defaults_when_fields_omitted() {
+        let schedule: SchedulingConfig = serde_json::from_str("{}").unwrap();
+        assert!(schedule.inclusion_windows.is_empty());
+        assert_eq!(schedule.min_elevation_deg, 0.0);
+        assert_eq!(schedule.min_samples, 0);
+    }
+
     #[test]
     fn test_separation_calculation() {
         // Test angular separation between two vectors
@@ -842,4 +4209,25 @@ mod tests {
         assert!((sep_rad_perp - PI/2.0).abs() < 0.001,
                 "Perpendicular vectors should have π/2 separation");
     }
+
+    #[test]
+    fn test_topocentric_parallax_shifts_moon_more_than_sun() {
+        let dt = Utc.with_ymd_and_hms(2025, 10, 5, 12, 0, 0).unwrap();
+        let jd = datetime_to_jd(dt);
+        let observer_ecef = geodetic_to_ecef(48.8566_f64.to_radians(), 2.3522_f64.to_radians(), 35.0);
+
+        let moon_geocentric = moon_position_eci(jd);
+        let moon_topo = topocentric(&moon_geocentric, &observer_ecef);
+        let moon_parallax_deg = angle_between(&moon_geocentric, &moon_topo).to_degrees();
+
+        let sun_geocentric = sun_position_eci(jd);
+        let sun_topo = topocentric(&sun_geocentric, &observer_ecef);
+        let sun_parallax_deg = angle_between(&sun_geocentric, &sun_topo).to_degrees();
+
+        // Lunar horizontal parallax is close to 1°; solar parallax is ~8.8″.
+        assert!(moon_parallax_deg > 0.5 && moon_parallax_deg < 1.1,
+                "Moon parallax should be close to 1°, got {moon_parallax_deg}");
+        assert!(sun_parallax_deg < 0.01, "Sun parallax should be a few arcseconds, got {sun_parallax_deg}");
+        assert!(moon_parallax_deg > sun_parallax_deg, "Moon parallax should dwarf solar parallax");
+    }
 }
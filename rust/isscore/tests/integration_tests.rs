@@ -1,8 +1,8 @@
 use std::collections::{BTreeMap, BTreeSet};
-use std::ffi::{CStr, CString};
+use std::ffi::{CStr, CString, c_void};
 
 // Import from the isscore library
-use isscore::{predict_transits, free_json};
+use isscore::{predict_transits, predict_transits_stream, predict_transits_multi, free_json};
 use serde::Deserialize;
 
 #[derive(Debug, serde::Deserialize)]
@@ -52,6 +52,13 @@ fn test_predict_transits_paris() {
         start_epoch,
         end_epoch,
         max_distance_km,
+        -1.0, // pressure_hpa: use default standard atmosphere
+        0.0,  // temperature_c: 0 °C (defaults only at or below absolute zero)
+        std::ptr::null(), // extra_bodies_csv: none
+        std::ptr::null(), // schedule_json: none
+        false, // columnar: classic array output
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
     );
     
     assert!(!json_ptr.is_null(), "FFI should return non-null pointer");
@@ -83,6 +90,134 @@ fn test_predict_transits_paris() {
     free_json(json_ptr);
 }
 
+extern "C" fn collect_event_json(event_json: *const std::os::raw::c_char, user_data: *mut c_void) {
+    let json = unsafe { CStr::from_ptr(event_json) }.to_string_lossy().into_owned();
+    let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+    events.push(json);
+}
+
+#[test]
+fn test_predict_transits_stream_matches_batch_event_count() {
+    // Same observer and start as test_predict_transits_paris, over a 15-day
+    // window; the streaming variant should deliver exactly as many events
+    // as the batch one returns.
+    let tle1 = CString::new("1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990").unwrap();
+    let tle2 = CString::new("2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279").unwrap();
+
+    let start_epoch = 1759622400i64;
+    let end_epoch = start_epoch + (15 * 86400);
+
+    let mut collected: Vec<String> = Vec::new();
+    let user_data = &mut collected as *mut Vec<String> as *mut c_void;
+
+    let count = predict_transits_stream(
+        tle1.as_ptr(),
+        tle2.as_ptr(),
+        48.8566,
+        2.3522,
+        35.0,
+        start_epoch,
+        end_epoch,
+        35.0,
+        -1.0,
+        0.0,
+        std::ptr::null(),
+        std::ptr::null(),
+        collect_event_json,
+        user_data,
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
+    );
+
+    assert_eq!(count as usize, collected.len(), "returned count should match events delivered");
+    assert!(!collected.is_empty(), "15-day window should produce at least one event");
+
+    for json in &collected {
+        let event: Result<Event, _> = serde_json::from_str(json);
+        assert!(event.is_ok(), "each streamed event should be valid single-event JSON: {json}");
+    }
+
+    let json_ptr = predict_transits(
+        tle1.as_ptr(),
+        tle2.as_ptr(),
+        48.8566,
+        2.3522,
+        35.0,
+        start_epoch,
+        end_epoch,
+        35.0,
+        -1.0,
+        0.0,
+        std::ptr::null(),
+        std::ptr::null(),
+        false,
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
+    );
+    let json_str = unsafe { CStr::from_ptr(json_ptr).to_string_lossy().into_owned() };
+    let batch_events: Vec<Event> = serde_json::from_str(&json_str).expect("batch result should be valid JSON");
+    free_json(json_ptr);
+
+    assert_eq!(collected.len(), batch_events.len(), "streaming and batch should find the same number of events");
+}
+
+#[test]
+fn test_predict_transits_multi_tags_events_by_satellite_name() {
+    // Two entries for the same underlying TLE under different names: the
+    // merged result should contain both names' events, in equal numbers,
+    // still time-sorted.
+    let tle1 = "1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990";
+    let tle2 = "2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279";
+
+    let start_epoch = 1759622400i64;
+    let end_epoch = start_epoch + (15 * 86400);
+
+    let satellites_json = serde_json::json!([
+        {"name": "ISS (ZARYA)", "tle1": tle1, "tle2": tle2},
+        {"name": "ISS (TEST-DOUBLE)", "tle1": tle1, "tle2": tle2},
+    ])
+    .to_string();
+    let satellites_json_c = CString::new(satellites_json).unwrap();
+
+    let json_ptr = predict_transits_multi(
+        satellites_json_c.as_ptr(),
+        48.8566,
+        2.3522,
+        35.0,
+        start_epoch,
+        end_epoch,
+        35.0,
+        -1.0,
+        0.0,
+        std::ptr::null(),
+        std::ptr::null(),
+        false,
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
+    );
+
+    assert!(!json_ptr.is_null());
+    let json_str = unsafe { CStr::from_ptr(json_ptr).to_string_lossy().into_owned() };
+    let events: Vec<Event> = serde_json::from_str(&json_str).expect("multi result should be valid JSON");
+    free_json(json_ptr);
+
+    let names: BTreeSet<_> = events.iter().map(|e| e.satellite.clone()).collect();
+    assert_eq!(
+        names,
+        BTreeSet::from(["ISS (ZARYA)".to_string(), "ISS (TEST-DOUBLE)".to_string()]),
+        "events should be tagged with both satellite names"
+    );
+
+    let zarya_count = events.iter().filter(|e| e.satellite == "ISS (ZARYA)").count();
+    let double_count = events.iter().filter(|e| e.satellite == "ISS (TEST-DOUBLE)").count();
+    assert_eq!(zarya_count, double_count, "identical TLEs should produce identical event counts");
+
+    let mut sorted_times: Vec<_> = events.iter().map(|e| e.time_utc.clone()).collect();
+    sorted_times.sort();
+    let times: Vec<_> = events.iter().map(|e| e.time_utc.clone()).collect();
+    assert_eq!(times, sorted_times, "merged multi-satellite events should be time-sorted");
+}
+
 #[derive(Debug, Deserialize)]
 struct SchemaFixture {
     #[serde(rename = "version")]
@@ -122,6 +257,13 @@ fn event_schema_matches_fixture() {
         start_epoch,
         end_epoch,
         35.0,
+        -1.0, // pressure_hpa: use default standard atmosphere
+        0.0,  // temperature_c: 0 °C (defaults only at or below absolute zero)
+        std::ptr::null(), // extra_bodies_csv: none
+        std::ptr::null(), // schedule_json: none
+        false, // columnar: classic array output
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
     );
 
     assert!(!json_ptr.is_null(), "FFI should not return null");
@@ -178,6 +320,74 @@ fn event_schema_matches_fixture() {
     free_json(json_ptr);
 }
 
+#[test]
+fn test_predict_transits_columnar_matches_array_output() {
+    // Same window as event_schema_matches_fixture; the columnar mode should
+    // carry the same data as the classic array, just shaped as parallel
+    // columns with an explicit schema version and column type map.
+    let tle1 = CString::new("1 25544U 98067A   25278.49802050  .00011384  00000+0  20935-3 0  9990").unwrap();
+    let tle2 = CString::new("2 25544  51.6327 120.3420 0000884 206.2421 153.8523 15.49697304532279").unwrap();
+
+    let start_epoch = 1759622400i64;
+    let end_epoch = start_epoch + (15 * 86400);
+
+    let array_ptr = predict_transits(
+        tle1.as_ptr(), tle2.as_ptr(), 48.8566, 2.3522, 35.0,
+        start_epoch, end_epoch, 35.0, -1.0, 0.0,
+        std::ptr::null(), std::ptr::null(), false,
+        std::ptr::null(), 0,
+    );
+    let array_json = unsafe { CStr::from_ptr(array_ptr).to_string_lossy().into_owned() };
+    let array_events: Vec<Event> = serde_json::from_str(&array_json).expect("array result should be valid JSON");
+    free_json(array_ptr);
+
+    let columnar_ptr = predict_transits(
+        tle1.as_ptr(), tle2.as_ptr(), 48.8566, 2.3522, 35.0,
+        start_epoch, end_epoch, 35.0, -1.0, 0.0,
+        std::ptr::null(), std::ptr::null(), true,
+        std::ptr::null(), 0,
+    );
+    assert!(!columnar_ptr.is_null());
+    let columnar_json = unsafe { CStr::from_ptr(columnar_ptr).to_string_lossy().into_owned() };
+    let columns: serde_json::Value = serde_json::from_str(&columnar_json).expect("columnar result should be valid JSON");
+    free_json(columnar_ptr);
+
+    assert_eq!(columns["schema_version"].as_u64(), Some(4));
+
+    // Same golden schema as event_schema_matches_fixture: every event key
+    // must have a column, and every scalar field's declared type must match.
+    let fixture: SchemaFixture = serde_json::from_str(include_str!("fixtures/event_schema.json"))
+        .expect("fixture JSON should parse");
+
+    let column_types = columns["column_types"].as_object().expect("column_types should be an object");
+    let actual_columns: BTreeSet<_> = column_types.keys().cloned().collect();
+    let expected_columns: BTreeSet<_> = fixture.event_keys.iter().cloned().collect();
+    assert_eq!(
+        actual_columns, expected_columns,
+        "column_types keys diverged from golden schema (update tests/fixtures/event_schema.json if intentional)"
+    );
+
+    for (field, expected_type) in &fixture.field_types {
+        let declared = column_types
+            .get(field)
+            .unwrap_or_else(|| panic!("Field '{field}' missing from column_types even though schema lists it"));
+        let expected_str = match expected_type {
+            ExpectedType::String => "string",
+            ExpectedType::Number => "number",
+        };
+        assert_eq!(declared, expected_str, "column_types['{field}'] should be '{expected_str}'");
+    }
+
+    let time_utc_column = columns["time_utc"].as_array().expect("time_utc should be a column array");
+    assert_eq!(time_utc_column.len(), array_events.len(), "columnar arrays should have one entry per event");
+
+    for (i, event) in array_events.iter().enumerate() {
+        assert_eq!(columns["time_utc"][i], event.time_utc);
+        assert_eq!(columns["sat_alt_deg"][i].as_f64(), Some(event.sat_alt_deg));
+        assert_eq!(columns["satellite"][i], event.satellite);
+    }
+}
+
 #[test]
 fn test_predict_transits_north_pole() {
     // Test with extreme coordinates (North Pole)
@@ -193,6 +403,13 @@ fn test_predict_transits_north_pole() {
         1759622400i64,
         1759622400i64 + 86400,
         35.0,
+        -1.0, // pressure_hpa: use default standard atmosphere
+        0.0,  // temperature_c: 0 °C (defaults only at or below absolute zero)
+        std::ptr::null(), // extra_bodies_csv: none
+        std::ptr::null(), // schedule_json: none
+        false, // columnar: classic array output
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
     );
     
     assert!(!json_ptr.is_null());
@@ -221,6 +438,13 @@ fn test_predict_transits_equator() {
         1759622400i64,
         1759622400i64 + 86400,
         35.0,
+        -1.0, // pressure_hpa: use default standard atmosphere
+        0.0,  // temperature_c: 0 °C (defaults only at or below absolute zero)
+        std::ptr::null(), // extra_bodies_csv: none
+        std::ptr::null(), // schedule_json: none
+        false, // columnar: classic array output
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
     );
     
     assert!(!json_ptr.is_null());
@@ -252,6 +476,13 @@ fn test_predict_transits_short_window() {
         start_epoch,
         end_epoch,
         35.0,
+        -1.0, // pressure_hpa: use default standard atmosphere
+        0.0,  // temperature_c: 0 °C (defaults only at or below absolute zero)
+        std::ptr::null(), // extra_bodies_csv: none
+        std::ptr::null(), // schedule_json: none
+        false, // columnar: classic array output
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
     );
     
     assert!(!json_ptr.is_null());
@@ -283,6 +514,13 @@ fn test_predict_transits_long_window() {
         start_epoch,
         end_epoch,
         35.0,
+        -1.0, // pressure_hpa: use default standard atmosphere
+        0.0,  // temperature_c: 0 °C (defaults only at or below absolute zero)
+        std::ptr::null(), // extra_bodies_csv: none
+        std::ptr::null(), // schedule_json: none
+        false, // columnar: classic array output
+        std::ptr::null(), // spk_kernel_data: no precise kernel
+        0, // spk_kernel_len
     );
     
     assert!(!json_ptr.is_null());